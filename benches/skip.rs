@@ -0,0 +1,106 @@
+//! Measures the time and peak heap memory used to skip over stream data.
+//!
+//! Run with `cargo bench --bench skip`.
+//! Peak memory should stay the same regardless of stream size, since skipping doesn't allocate.
+
+// only `fsbex` is used here, but all dependencies of the library are linked
+#![allow(unused_crate_dependencies)]
+
+#[path = "../src/fixture.rs"]
+mod fixture;
+
+use fsbex::Bank;
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    convert::Infallible,
+    io::{Read, Result as IoResult},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+// tracks the current and peak number of bytes allocated on the heap
+struct CountingAllocator;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+
+        if !ptr.is_null() {
+            let current = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            let _ = PEAK.fetch_max(current, Ordering::Relaxed);
+        }
+
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        let _ = CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+// A sound bank whose stream data is generated on the fly, so large banks don't need to be stored in memory.
+struct GeneratedBank {
+    header: Vec<u8>,
+    position: usize,
+    len: usize,
+}
+
+impl GeneratedBank {
+    // PCM16 mono streams of the given sizes, which must be multiples of 32 bytes
+    fn new(sizes: &[u32]) -> Self {
+        let header = fixture::pcm16_headers(sizes);
+        let len = header.len() + sizes.iter().sum::<u32>() as usize;
+
+        Self {
+            header,
+            position: 0,
+            len,
+        }
+    }
+}
+
+impl Read for GeneratedBank {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        let len = buf.len().min(self.len - self.position);
+
+        for (byte, position) in buf[..len].iter_mut().zip(self.position..) {
+            *byte = self.header.get(position).copied().unwrap_or(0);
+        }
+
+        self.position += len;
+        Ok(len)
+    }
+}
+
+fn main() {
+    for size_mib in [1, 64, 512] {
+        let size = size_mib << 20;
+        let source = GeneratedBank::new(&[size, size]);
+
+        let baseline = CURRENT.load(Ordering::Relaxed);
+        PEAK.store(baseline, Ordering::Relaxed);
+        let start = Instant::now();
+
+        // streams aren't read within the callback, so all stream data is skipped
+        Bank::new(source)
+            .unwrap()
+            .read_streams(|_| Ok::<_, Infallible>(()))
+            .unwrap();
+
+        let elapsed = start.elapsed();
+        let peak = PEAK.load(Ordering::Relaxed) - baseline;
+
+        println!(
+            "skip 2 streams of {size_mib:>3} MiB: {elapsed:>12.3?}, peak heap usage {peak} bytes"
+        );
+
+        assert!(peak < 1 << 20, "skipping stream data should not allocate");
+    }
+}
@@ -0,0 +1,481 @@
+use crate::bank::{DecodeError, StreamReadError};
+use crate::header::{
+    error::{HeaderError, HeaderErrorKind},
+    options::{Diagnostics, Limits, ParseOptions, ParseWarning},
+    AudioFormat, BankFlags, BankHeader, Header, StreamInfo,
+};
+use crate::read::{ReadError, Reader};
+use crate::stream::{Stream, StreamView};
+use crate::validate::{validate, ValidationReport};
+use std::{
+    io::{Cursor, Error as IoError, Read, Result as IoResult},
+    num::NonZeroU32,
+};
+use tokio::io::{copy, sink, AsyncRead, AsyncReadExt, Chain};
+
+// the number of bytes at the start of a sound bank needed to compute the size of its headers
+const PREFIX_LEN: usize = 20;
+
+/// An FMOD sound bank that is read asynchronously, available with the `async` feature.
+///
+/// This is the asynchronous version of [`Bank`], for sources implementing [`AsyncRead`] from `tokio`.
+/// The file header is read into memory asynchronously and then parsed in the same way as for [`Bank`],
+/// so parsing succeeds and fails in the same cases, with the same errors.
+/// Streams are read with [`AsyncStreamIter`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use fsbex::AsyncBank;
+/// use std::error::Error;
+/// use tokio::io::AsyncRead;
+///
+/// async fn read_streams<R: AsyncRead + Unpin>(source: R) -> Result<(), Box<dyn Error>> {
+///     let mut streams = AsyncBank::new(source).await?.try_streams();
+///
+///     while let Some(stream) = streams.next().await {
+///         let stream = stream?;
+///         println!("read stream with {} samples", stream.sample_count());
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+///
+/// [`Bank`]: crate::Bank
+#[derive(Debug)]
+pub struct AsyncBank<R: AsyncRead + Unpin> {
+    header: Header,
+    // data that was read ahead of time while parsing the file header, followed by the rest of the source
+    source: Chain<Cursor<Vec<u8>>, R>,
+    position: u64,
+    limits: Limits,
+}
+
+impl<R: AsyncRead + Unpin> AsyncBank<R> {
+    /// Creates a new [`AsyncBank<R>`] by parsing from an asynchronous I/O stream.
+    ///
+    /// See [`Bank::new`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if parsing of the sound bank's file header failed.
+    /// See [`DecodeError`] for more information.
+    ///
+    /// [`Bank::new`]: crate::Bank::new
+    pub async fn new(source: R) -> Result<Self, DecodeError> {
+        Self::new_with(source, &ParseOptions::default()).await
+    }
+
+    /// Creates a new [`AsyncBank<R>`] by parsing from an asynchronous I/O stream, using the given [`ParseOptions`].
+    ///
+    /// The file header is read into memory before it is parsed,
+    /// so its size counts towards the limit set by [`Limits::max_allocation`].
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if parsing of the sound bank's file header failed.
+    /// See [`DecodeError`] for more information.
+    #[allow(clippy::missing_panics_doc)]
+    pub async fn new_with(mut source: R, options: &ParseOptions) -> Result<Self, DecodeError> {
+        skip_base_offset(&mut source, options.base_offset).await?;
+
+        let mut buf = Vec::new();
+        let mut error = prefetch(&mut source, &mut buf, PREFIX_LEN as u64).await;
+        let mut requested = headers_len(&buf).unwrap_or(PREFIX_LEN as u64);
+
+        loop {
+            if requested > options.limits.allocation {
+                return Err(HeaderError::new(HeaderErrorKind::AllocationLimit {
+                    limit: options.limits.allocation,
+                })
+                .into());
+            }
+
+            if error.is_none() {
+                let len = requested - buf.len() as u64;
+                error = prefetch(&mut source, &mut buf, len).await;
+            }
+
+            let exhausted = error.is_some() || (buf.len() as u64) < requested;
+            let data = Prefetched::new(&buf, error.take());
+            let mut reader = Reader::with_position(data, options.base_offset);
+
+            match Header::parse_with(&mut reader, Diagnostics::new(options)) {
+                Ok(header) => {
+                    let position = reader.position();
+                    let consumed = (position - options.base_offset)
+                        .try_into()
+                        .expect("parsing can't read more data than was read ahead of time");
+                    let rest = buf.split_off(consumed);
+
+                    return Ok(Self {
+                        header,
+                        source: AsyncReadExt::chain(Cursor::new(rest), source),
+                        position,
+                        limits: options.limits,
+                    });
+                }
+                // In non-strict mode, stream headers can be larger than their declared size.
+                // If parsing ran out of data, more data is read and parsing is retried.
+                Err(_) if !exhausted && reader.position() - options.base_offset == requested => {
+                    requested *= 2;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Checks the sound bank for inconsistencies between its headers, without reading any stream data.
+    ///
+    /// See [`Bank::validate`] for more information.
+    ///
+    /// [`Bank::validate`]: crate::Bank::validate
+    #[must_use]
+    pub fn validate(&self) -> ValidationReport {
+        validate(&self.header)
+    }
+
+    /// Returns the problems that were recovered from while parsing the sound bank.
+    ///
+    /// See [`Bank::warnings`] for more information.
+    ///
+    /// [`Bank::warnings`]: crate::Bank::warnings
+    #[must_use]
+    pub fn warnings(&self) -> &[ParseWarning] {
+        &self.header.warnings
+    }
+
+    /// Returns information from the file header of the sound bank.
+    ///
+    /// See [`BankHeader`] for more information.
+    #[must_use]
+    pub fn header(&self) -> &BankHeader {
+        &self.header.base
+    }
+
+    /// Returns the audio format of streams in the sound bank.
+    #[must_use]
+    pub fn format(&self) -> AudioFormat {
+        self.header.format
+    }
+
+    /// Returns the number of streams in the sound bank.
+    #[must_use]
+    pub fn num_streams(&self) -> NonZeroU32 {
+        self.header.num_streams()
+    }
+
+    /// Returns an iterator over the metadata of all streams in the sound bank, in order.
+    ///
+    /// See [`Bank::stream_infos`] for more information.
+    ///
+    /// [`Bank::stream_infos`]: crate::Bank::stream_infos
+    pub fn stream_infos(&self) -> impl ExactSizeIterator<Item = StreamView<'_>> + '_ {
+        self.header.stream_views()
+    }
+
+    /// Converts this [`AsyncBank<R>`] into an asynchronous iterator that reads streams and reports errors.
+    ///
+    /// See [`AsyncStreamIter`] for more information.
+    pub fn try_streams(self) -> AsyncStreamIter<R> {
+        AsyncStreamIter {
+            index: 0,
+            failed: false,
+            format: self.header.format,
+            flags: self.header.base.flags,
+            info: self.header.stream_info,
+            source: self.source,
+            position: self.position,
+            max_allocation: self.limits.allocation,
+        }
+    }
+}
+
+/// An asynchronous, fallible iterator over sound bank streams, available with the `async` feature.
+///
+/// This type is returned from [`AsyncBank::try_streams`], and is the asynchronous version of [`TryStreamIter`].
+/// Calling [`AsyncStreamIter::next`] returns `Some(Ok(Stream))` if a stream was successfully read,
+/// and `Some(Err(StreamReadError))` if reading failed (e.g. because the file was truncated).
+/// After an error is returned, the iterator is exhausted and only returns `None`.
+///
+/// [`TryStreamIter`]: crate::TryStreamIter
+#[derive(Debug)]
+pub struct AsyncStreamIter<R: AsyncRead + Unpin> {
+    index: u32,
+    failed: bool,
+    format: AudioFormat,
+    flags: BankFlags,
+    info: Box<[StreamInfo]>,
+    source: Chain<Cursor<Vec<u8>>, R>,
+    position: u64,
+    max_allocation: u64,
+}
+
+impl<R: AsyncRead + Unpin> AsyncStreamIter<R> {
+    /// Reads the next stream from the sound bank.
+    ///
+    /// Returns `None` once all streams were read or after reading failed.
+    pub async fn next(&mut self) -> Option<Result<Stream, StreamReadError>> {
+        if self.failed {
+            return None;
+        }
+
+        let index = self.index;
+        let info = self.info.get(index as usize).cloned()?;
+        self.index += 1;
+
+        let stream = self.read_stream(info).await.map_err(StreamReadError::factory(index));
+
+        // a failed read leaves the reader at an unknown position, so the following streams can't be read
+        self.failed = stream.is_err();
+
+        Some(stream)
+    }
+
+    /// Returns the number of streams that are left to be read.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        if self.failed {
+            0
+        } else {
+            self.info.len() - self.index as usize
+        }
+    }
+
+    /// Returns `true` if there are no streams left to be read.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    async fn read_stream(&mut self, info: StreamInfo) -> Result<Stream, ReadError> {
+        let size = info.size.get();
+        let mut buf = Vec::new();
+
+        let error = if u64::from(size) > self.max_allocation {
+            None
+        } else {
+            prefetch(&mut self.source, &mut buf, size.into()).await
+        };
+
+        // Stream data is parsed from the data that was read, so that errors are the same as for `TryStreamIter`.
+        // This only copies the data if reading failed.
+        let data = if error.is_none() && buf.len() == size as usize {
+            self.position += u64::from(size);
+            buf
+        } else {
+            Reader::with_position(Prefetched::new(&buf, error), self.position)
+                .take_within(size as usize, self.max_allocation)?
+        };
+
+        Ok(Stream::new(self.format, self.flags, info, data.into_boxed_slice()))
+    }
+}
+
+// Data read ahead of time from an asynchronous source, which is parsed with the same code as synchronous sources.
+// If reading stopped because of an I/O error, the error is returned after all data that was read,
+// so it is reported at the same position as when reading synchronously.
+struct Prefetched<'data> {
+    data: &'data [u8],
+    error: Option<IoError>,
+}
+
+impl<'data> Prefetched<'data> {
+    fn new(data: &'data [u8], error: Option<IoError>) -> Self {
+        Self { data, error }
+    }
+}
+
+impl Read for Prefetched<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        if self.data.is_empty() && !buf.is_empty() {
+            if let Some(e) = self.error.take() {
+                return Err(e);
+            }
+        }
+
+        Read::read(&mut self.data, buf)
+    }
+}
+
+// Reads up to `len` bytes into `buf`, stopping early at the end of the data.
+// Data read before an I/O error is kept, and the error is returned.
+async fn prefetch<R: AsyncRead + Unpin>(
+    source: &mut R,
+    buf: &mut Vec<u8>,
+    len: u64,
+) -> Option<IoError> {
+    source.take(len).read_to_end(buf).await.err()
+}
+
+async fn skip_base_offset<R: AsyncRead + Unpin>(
+    source: &mut R,
+    offset: u64,
+) -> Result<(), HeaderError> {
+    let (skipped, error) = match copy(&mut source.take(offset), &mut sink()).await {
+        Ok(skipped) => (skipped, None),
+        Err(e) => (0, Some(e)),
+    };
+
+    // errors are reported in the same way as when skipping data from a synchronous source
+    Reader::with_position(Prefetched::new(&[], error), skipped)
+        .skip(offset - skipped)
+        .map_err(HeaderError::factory(HeaderErrorKind::Magic))
+}
+
+// The file header consists of the base header (which is larger in version 0), stream headers and the name table.
+// Their sizes are stored near the start of the file header.
+fn headers_len(prefix: &[u8]) -> Option<u64> {
+    let field = |offset: usize| {
+        prefix
+            .get(offset..offset + 4)?
+            .try_into()
+            .ok()
+            .map(u32::from_le_bytes)
+            .map(u64::from)
+    };
+
+    let base_header_size = if field(4)? == 0 { 64 } else { 60 };
+
+    Some(base_header_size + field(12)? + field(16)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::AsyncBank;
+    use crate::bank::{test::test_bank, Bank};
+    use crate::header::options::{ParseOptions, ParseWarning};
+    use std::{
+        future::Future,
+        io::{Error as IoError, ErrorKind, Read, Result as IoResult},
+        pin::{pin, Pin},
+        sync::Arc,
+        task::{Context, Poll, Wake, Waker},
+    };
+    use tokio::io::{AsyncRead, ReadBuf};
+
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    // In-memory sources never wait, so futures can be polled to completion without a runtime.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut cx = Context::from_waker(&waker);
+
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn read_streams() {
+        let data = test_bank(&[32, 64]);
+
+        let bank = block_on(AsyncBank::new(data.as_slice())).unwrap();
+        assert_eq!(bank.num_streams().get(), 2);
+        assert_eq!(bank.stream_infos().nth(1).unwrap().data_offset(), 108);
+
+        let mut streams = bank.try_streams();
+        assert_eq!(streams.len(), 2);
+
+        let expected: Vec<_> = Bank::new(data.as_slice()).unwrap().into_iter().collect();
+        for expected in expected {
+            let stream = block_on(streams.next()).unwrap().unwrap();
+            assert_eq!(stream, expected);
+        }
+
+        assert!(streams.is_empty());
+        assert!(block_on(streams.next()).is_none());
+    }
+
+    #[test]
+    fn report_same_errors_as_bank() {
+        // truncated in the stream headers
+        let data = test_bank(&[32, 64]);
+        let async_err = block_on(AsyncBank::new(&data[..65])).unwrap_err();
+        let err = Bank::new(&data[..65]).unwrap_err();
+        assert_eq!(async_err.kind(), err.kind());
+        assert_eq!(async_err.stream_index(), err.stream_index());
+        assert_eq!(async_err.position(), err.position());
+
+        // truncated in the stream data
+        let data = &data[..data.len() - 40];
+        let bank = block_on(AsyncBank::new(data)).unwrap();
+        let mut streams = bank.try_streams();
+        assert!(block_on(streams.next()).unwrap().is_ok());
+
+        let async_err = block_on(streams.next()).unwrap().unwrap_err();
+        let err = Bank::new(data).unwrap().try_streams().nth(1).unwrap().unwrap_err();
+        assert_eq!(async_err.index(), err.index());
+        assert_eq!(async_err.kind(), err.kind());
+        assert_eq!(async_err.position(), err.position());
+        assert!(block_on(streams.next()).is_none());
+    }
+
+    // returns an I/O error after the data is read
+    #[derive(Debug)]
+    struct FailingReader<'data>(&'data [u8]);
+
+    impl Read for FailingReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+            if self.0.is_empty() {
+                return Err(IoError::from(ErrorKind::ConnectionReset));
+            }
+            self.0.read(buf)
+        }
+    }
+
+    impl AsyncRead for FailingReader<'_> {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<IoResult<()>> {
+            let len = self.get_mut().read(buf.initialize_unfilled())?;
+            buf.advance(len);
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn report_io_errors_at_same_position() {
+        let data = test_bank(&[32]);
+
+        let async_err = block_on(AsyncBank::new(FailingReader(&data[..30]))).unwrap_err();
+        let err = Bank::new(FailingReader(&data[..30])).unwrap_err();
+        assert_eq!(async_err.kind(), err.kind());
+        assert_eq!(async_err.read_kind(), err.read_kind());
+        assert_eq!(async_err.position(), Some(30));
+        assert_eq!(async_err.position(), err.position());
+    }
+
+    #[test]
+    fn read_oversized_headers_leniently() {
+        // the stream headers are declared to be 0 bytes long, but contain an 8-byte stream header
+        let mut data = test_bank(&[32]);
+        data[12..16].copy_from_slice(&0u32.to_le_bytes());
+
+        let options = ParseOptions::default().strict(false).base_offset(3);
+        data.splice(0..0, [0; 3]).for_each(drop);
+
+        let bank = block_on(AsyncBank::new_with(data.as_slice(), &options)).unwrap();
+        assert_eq!(
+            bank.warnings(),
+            [ParseWarning::WrongHeaderSize {
+                expected: 60,
+                actual: 68
+            }]
+        );
+
+        let stream = block_on(bank.try_streams().next()).unwrap().unwrap();
+        let expected = Bank::new_with(data.as_slice(), &options).unwrap().into_iter().next();
+        assert_eq!(Some(stream), expected);
+    }
+}
@@ -1,13 +1,25 @@
-use crate::header::{error::HeaderError, AudioFormat, Header};
-use crate::read::{ReadError, Reader};
-use crate::stream::{LazyStream, Stream, StreamIntoIter};
+#[cfg(feature = "rayon")]
+use crate::encode::EncodeOptions;
+use crate::header::{
+    error::{ChunkErrorKind, HeaderError, HeaderErrorKind, NameErrorKind, StreamErrorKind},
+    options::{Diagnostics, Limits, ParseOptions, ParseWarning},
+    AudioFormat, BankHeader, Header,
+};
+#[cfg(feature = "rayon")]
+use crate::parallel::{par_extract, ExtractError};
+use crate::read::{ReadError, ReadErrorKind, Reader};
+use crate::salvage::{salvage, Salvage};
+use crate::stream::{LazyStream, Stream, StreamIntoIter, StreamView, TryStreamIter};
+use crate::validate::{validate, ValidationReport};
+#[cfg(feature = "rayon")]
+use std::io::Write;
 use std::{
     error::Error,
     fmt::{Display, Formatter, Result as FmtResult},
-    io::Read,
+    io::{Read, Seek, SeekFrom, Take},
     num::NonZeroU32,
+    ops::ControlFlow,
 };
-use tap::Pipe;
 
 /// An FMOD sound bank.
 ///
@@ -50,6 +62,7 @@ use tap::Pipe;
 pub struct Bank<R: Read> {
     header: Header,
     read: Reader<R>,
+    limits: Limits,
 }
 
 impl<R: Read> Bank<R> {
@@ -67,9 +80,67 @@ impl<R: Read> Bank<R> {
     /// [`File`]: std::fs::File
     /// [`BufReader`]: std::io::BufReader
     pub fn new(source: R) -> Result<Self, DecodeError> {
+        Self::new_with(source, &ParseOptions::default())
+    }
+
+    /// Creates a new [`Bank<R>`] by parsing from an I/O stream, using the given [`ParseOptions`].
+    ///
+    /// See [`Bank::new`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if parsing of the sound bank's file header failed.
+    /// See [`DecodeError`] for more information.
+    pub fn new_with(source: R, options: &ParseOptions) -> Result<Self, DecodeError> {
         let mut read = Reader::new(source);
-        let header = Header::parse(&mut read)?;
-        Ok(Self { header, read })
+
+        // data before the sound bank is skipped, so that positions are still relative to the start of the source
+        read.skip(options.base_offset)
+            .map_err(HeaderError::factory(HeaderErrorKind::Magic))?;
+
+        let header = Header::parse_with(&mut read, Diagnostics::new(options))?;
+        Ok(Self {
+            header,
+            read,
+            limits: options.limits,
+        })
+    }
+
+    /// Checks the sound bank for inconsistencies between its headers, without reading any stream data.
+    ///
+    /// The following properties are checked:
+    /// - the sizes of all streams add up to the total size of stream data
+    /// - the number of samples in each stream matches the size of its data, for PCM and ADPCM formats
+    /// - loops lie within their streams
+    /// - Vorbis streams refer to known setup headers
+    /// - stream names take up the entire name table
+    ///
+    /// Stream data offsets are always aligned to 32 bytes, since they are stored in units of 32 bytes.
+    /// Sound banks where stream data offsets are not increasing fail to parse
+    /// with [`HeaderErrorKind::UnorderedStreamOffset`] or [`HeaderErrorKind::ZeroStreamSize`] instead,
+    /// even in non-strict mode, since the sizes of their streams can't be determined.
+    ///
+    /// See [`ValidationReport`] for more information.
+    #[must_use]
+    pub fn validate(&self) -> ValidationReport {
+        validate(&self.header)
+    }
+
+    /// Returns the problems that were recovered from while parsing the sound bank.
+    ///
+    /// This is always empty unless the sound bank was parsed in non-strict mode.
+    /// See [`ParseOptions::strict`] for more information.
+    #[must_use]
+    pub fn warnings(&self) -> &[ParseWarning] {
+        &self.header.warnings
+    }
+
+    /// Returns information from the file header of the sound bank.
+    ///
+    /// See [`BankHeader`] for more information.
+    #[must_use]
+    pub fn header(&self) -> &BankHeader {
+        &self.header.base
     }
 
     /// Returns the audio format of streams in the sound bank.
@@ -81,22 +152,69 @@ impl<R: Read> Bank<R> {
     }
 
     /// Returns the number of streams in the sound bank.
-    #[allow(clippy::missing_panics_doc)]
     #[must_use]
     pub fn num_streams(&self) -> NonZeroU32 {
-        self.header
-            .stream_info
-            .len()
-            .pipe(u32::try_from)
-            .expect("stream count was already validated to be NonZeroU32")
-            .try_into()
-            .expect("stream count was already validated to be NonZeroU32")
+        self.header.num_streams()
+    }
+
+    /// Returns an iterator over the metadata of all streams in the sound bank, in order.
+    ///
+    /// Unlike [`Bank::read_streams`] and [`Bank::into_iter`], this doesn't read any stream data
+    /// or consume the [`Bank<R>`], since metadata is parsed along with the file header.
+    /// See [`StreamView`] for more information.
+    pub fn stream_infos(&self) -> impl ExactSizeIterator<Item = StreamView<'_>> + '_ {
+        self.header.stream_views()
     }
 
     /// Sequentially reads streams from the sound bank, consuming this [`Bank<R>`].
     /// Streams can be accessed within the function `f` as they are read.
     /// See [`LazyStream`] for more information.
     ///
+    /// `f` can stop reading early by returning [`ControlFlow::Break`] instead of `()`
+    /// (see [`StreamControl`]), in which case no more streams are read.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if:
+    /// - an error was returned from `f`
+    /// - the underlying reader failed to advance to the next stream
+    ///
+    /// See [`LazyStreamError`] for more information.
+    pub fn read_streams<F, C, E>(self, f: F) -> Result<(), LazyStreamError<E>>
+    where
+        F: FnMut(LazyStream<'_, R>) -> Result<C, E>,
+        C: StreamControl,
+    {
+        self.read_streams_filtered(|_| true, f)
+    }
+
+    /// Sequentially reads the streams whose metadata matches `filter`, consuming this [`Bank<R>`].
+    ///
+    /// `filter` is called with the metadata of every stream (see [`StreamView`]),
+    /// so streams can be chosen by index, name or any other property.
+    /// The data of streams that don't match is skipped without being buffered,
+    /// and `f` is only called with the streams that match.
+    ///
+    /// See [`Bank::read_streams`] for more information.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use fsbex::{Bank, LazyStreamError};
+    /// use std::{fs::File, io::{BufReader, Error as IoError}, ops::ControlFlow};
+    ///
+    /// fn find_music(bank: Bank<BufReader<File>>) -> Result<(), LazyStreamError<IoError>> {
+    ///     bank.read_streams_filtered(
+    ///         |stream| stream.name().is_some_and(|name| name.starts_with("music_")),
+    ///         |stream| {
+    ///             let file = File::create(format!("{}.wav", stream.name().unwrap()))?;
+    ///             stream.write(file).map_err(IoError::other)?;
+    ///             Ok(ControlFlow::<()>::Continue(()))
+    ///         },
+    ///     )
+    /// }
+    /// ```
+    ///
     /// # Errors
     ///
     /// This function returns an error if:
@@ -104,22 +222,34 @@ impl<R: Read> Bank<R> {
     /// - the underlying reader failed to advance to the next stream
     ///
     /// See [`LazyStreamError`] for more information.
-    pub fn read_streams<F, E>(mut self, f: F) -> Result<(), LazyStreamError<E>>
+    pub fn read_streams_filtered<P, F, C, E>(
+        mut self,
+        mut filter: P,
+        mut f: F,
+    ) -> Result<(), LazyStreamError<E>>
     where
-        F: Fn(LazyStream<'_, R>) -> Result<(), E>,
+        P: FnMut(StreamView<'_>) -> bool,
+        F: FnMut(LazyStream<'_, R>) -> Result<C, E>,
+        C: StreamControl,
     {
         for (info, index) in self.header.stream_info.iter().zip(0..) {
-            let size = info.size.get() as usize;
+            let size = u64::from(info.size.get());
             let start_pos = self.read.position();
 
-            f(LazyStream::new(
-                index,
-                self.header.format,
-                self.header.flags,
-                info,
-                &mut self.read,
-            ))
-            .map_err(LazyStreamError::from_other(index))?;
+            if filter(StreamView::new(index, info)) {
+                let control = f(LazyStream::new(
+                    index,
+                    self.header.format,
+                    self.header.base.flags,
+                    info,
+                    &mut self.read,
+                ))
+                .map_err(LazyStreamError::from_other(index))?;
+
+                if !control.should_continue() {
+                    break;
+                }
+            }
 
             self.read
                 .advance_to(start_pos + size)
@@ -127,16 +257,156 @@ impl<R: Read> Bank<R> {
         }
         Ok(())
     }
+
+    /// Reads every stream that can be recovered from a sound bank whose stream data was cut short
+    /// (e.g. by an interrupted download or an incomplete file carve), consuming this [`Bank<R>`].
+    ///
+    /// Unlike [`Bank::try_streams`], which stops at the first stream that can't be read completely,
+    /// this also recovers the audio of that stream that can still be decoded,
+    /// and reports how many of its samples were recovered.
+    /// Streams of sound banks that aren't truncated are all read completely.
+    /// See [`Salvage`] for more information.
+    pub fn salvage(mut self) -> Salvage {
+        salvage(
+            self.header.format,
+            self.header.base.flags,
+            self.header.stream_info,
+            &mut self.read,
+            self.limits.allocation,
+        )
+    }
+
+    /// Converts this [`Bank<R>`] into an iterator that reads streams and reports errors.
+    ///
+    /// Unlike [`Bank::into_iter`], which stops silently when a stream can't be read,
+    /// the returned iterator yields a [`StreamReadError`] describing the failure.
+    /// See [`TryStreamIter`] for more information.
+    pub fn try_streams(self) -> TryStreamIter<R> {
+        TryStreamIter::new(
+            self.header.format,
+            self.header.base.flags,
+            self.header.stream_info,
+            self.read,
+            self.limits.allocation,
+        )
+    }
+}
+
+impl<R: Read + Seek> Bank<Take<R>> {
+    /// Creates a new [`Bank`] from a sound bank embedded in a larger source, such as a game archive.
+    ///
+    /// The sound bank starts `offset` bytes into `source` and is `length` bytes long.
+    /// It is treated as an isolated sound bank: byte positions in errors and stream data offsets
+    /// are measured from the start of the sound bank, and no data is read past its end.
+    ///
+    /// See [`Bank::new`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if seeking to `offset` or parsing of the sound bank's file header failed.
+    /// See [`DecodeError`] for more information.
+    pub fn new_at(source: R, offset: u64, length: u64) -> Result<Self, DecodeError> {
+        Self::new_at_with(source, offset, length, &ParseOptions::default())
+    }
+
+    /// Creates a new [`Bank`] from a sound bank embedded in a larger source, using the given [`ParseOptions`].
+    ///
+    /// The [`ParseOptions::base_offset`] of `options` is ignored, since `offset` gives the start of the sound bank.
+    ///
+    /// See [`Bank::new_at`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if seeking to `offset` or parsing of the sound bank's file header failed.
+    /// A failed seek is reported as [`HeaderErrorKind::Seek`] at position `offset`.
+    /// See [`DecodeError`] for more information.
+    pub fn new_at_with(
+        mut source: R,
+        offset: u64,
+        length: u64,
+        options: &ParseOptions,
+    ) -> Result<Self, DecodeError> {
+        let _ = source.seek(SeekFrom::Start(offset)).map_err(|e| {
+            HeaderError::new_with_source(HeaderErrorKind::Seek, ReadError::io(offset, e))
+        })?;
+
+        let options = ParseOptions {
+            base_offset: 0,
+            ..options.clone()
+        };
+        Bank::new_with(source.take(length), &options)
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<R: Read + Seek + Send> Bank<R> {
+    /// Encodes every stream across multiple threads, consuming this [`Bank<R>`].
+    ///
+    /// For each stream, `f` is called with its metadata to create the writer that its audio is written to.
+    /// The data of each stream is read separately by seeking to it, and encoding happens in parallel.
+    /// Results are returned in the order of stream indices, regardless of the order streams finish in.
+    /// A stream that fails to be read or encoded doesn't stop other streams from being extracted.
+    ///
+    /// Sound banks in memory can be extracted by wrapping the data in a [`Cursor`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use fsbex::Bank;
+    /// use std::{error::Error, fs::File, io::BufWriter};
+    ///
+    /// fn extract_all() -> Result<(), Box<dyn Error>> {
+    ///     let bank = Bank::new(File::open("example.fsb")?)?;
+    ///
+    ///     let results = bank.par_extract(|stream| {
+    ///         let name = stream.name().unwrap_or("unnamed");
+    ///         BufWriter::new(File::create(format!("{}_{name}", stream.index())).unwrap())
+    ///     });
+    ///
+    ///     for result in results {
+    ///         let _writer = result?;
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// [`Cursor`]: std::io::Cursor
+    pub fn par_extract<F, W>(self, f: F) -> Vec<Result<W, ExtractError>>
+    where
+        F: Fn(StreamView<'_>) -> W + Sync,
+        W: Write + Send,
+    {
+        self.par_extract_with(&EncodeOptions::default(), f)
+    }
+
+    /// Encodes every stream across multiple threads using the given [`EncodeOptions`], consuming this [`Bank<R>`].
+    ///
+    /// See [`Bank::par_extract`] for more information.
+    pub fn par_extract_with<F, W>(
+        self,
+        options: &EncodeOptions,
+        f: F,
+    ) -> Vec<Result<W, ExtractError>>
+    where
+        F: Fn(StreamView<'_>) -> W + Sync,
+        W: Write + Send,
+    {
+        par_extract(
+            self.header.format,
+            self.header.base.flags,
+            &self.header.stream_info,
+            self.read,
+            self.limits.allocation,
+            options,
+            f,
+        )
+    }
 }
 
 impl<R: Read> From<Bank<R>> for StreamIntoIter<R> {
     fn from(value: Bank<R>) -> Self {
-        Self::new(
-            value.header.format,
-            value.header.flags,
-            value.header.stream_info,
-            value.read,
-        )
+        value.try_streams().into()
     }
 }
 
@@ -153,11 +423,90 @@ impl<R: Read> IntoIterator for Bank<R> {
 ///
 /// This type is returned from [`Bank::new`] when file header parsing fails.
 /// This can be caused by invalid data or the underlying reader encountering an I/O error.
+///
+/// The cause of the error can be inspected with [`DecodeError::kind`] and the more specific kinds
+/// returned by other methods:
+///
+/// ```no_run
+/// use fsbex::{Bank, HeaderErrorKind, ReadErrorKind};
+/// use std::fs::File;
+///
+/// let file = File::open("example.fsb")?;
+///
+/// match Bank::new(file) {
+///     Ok(bank) => { /* ... */ }
+///     Err(e) if matches!(e.kind(), HeaderErrorKind::UnknownAudioFormat { .. }) => {
+///         eprintln!("unsupported audio format");
+///     }
+///     Err(e) if matches!(e.read_kind(), Some(ReadErrorKind::Incomplete(_))) => {
+///         eprintln!("file was truncated at byte {:?}", e.position());
+///     }
+///     Err(e) => return Err(e.into()),
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
 #[derive(Debug)]
 pub struct DecodeError {
     inner: Box<HeaderError>,
 }
 
+impl DecodeError {
+    /// Returns the kind of error that occurred while parsing the file header.
+    #[must_use]
+    pub fn kind(&self) -> HeaderErrorKind {
+        self.inner.kind()
+    }
+
+    /// Returns the kind of error that occurred while parsing a stream header, if any.
+    ///
+    /// This is present when [`DecodeError::kind`] is [`HeaderErrorKind::StreamHeader`].
+    #[must_use]
+    pub fn stream_kind(&self) -> Option<StreamErrorKind> {
+        self.inner.stream_kind()
+    }
+
+    /// Returns the kind of error that occurred while parsing a stream header chunk, if any.
+    ///
+    /// This is present when [`DecodeError::stream_kind`] is [`StreamErrorKind::Chunk`].
+    #[must_use]
+    pub fn chunk_kind(&self) -> Option<ChunkErrorKind> {
+        self.inner.chunk_kind()
+    }
+
+    /// Returns the kind of error that occurred while reading stream names, if any.
+    ///
+    /// This is present when [`DecodeError::kind`] is [`HeaderErrorKind::NameTable`].
+    #[must_use]
+    pub fn name_kind(&self) -> Option<NameErrorKind> {
+        self.inner.name_kind()
+    }
+
+    /// Returns the kind of error that occurred while reading data, if the error was caused by a failed read.
+    #[must_use]
+    pub fn read_kind(&self) -> Option<ReadErrorKind> {
+        self.inner.read_err().map(ReadError::kind)
+    }
+
+    /// Returns the index of the stream where the error occurred, if it was caused by a specific stream.
+    #[must_use]
+    pub fn stream_index(&self) -> Option<u32> {
+        self.inner.stream_index()
+    }
+
+    /// Returns the index of the stream header chunk where the error occurred, if any.
+    /// This is the index of the chunk within the header of the stream at [`DecodeError::stream_index`].
+    #[must_use]
+    pub fn chunk_index(&self) -> Option<u32> {
+        self.inner.chunk_index()
+    }
+
+    /// Returns the byte position in the file where reading failed, if the error was caused by a failed read.
+    #[must_use]
+    pub fn position(&self) -> Option<u64> {
+        self.inner.read_err().map(ReadError::position)
+    }
+}
+
 impl From<HeaderError> for DecodeError {
     fn from(value: HeaderError) -> Self {
         Self {
@@ -178,6 +527,26 @@ impl Error for DecodeError {
     }
 }
 
+/// A value returned from the function given to [`Bank::read_streams`] that decides whether to keep reading streams.
+///
+/// This is implemented for `()`, which always continues, and [`ControlFlow`], which stops on [`ControlFlow::Break`].
+pub trait StreamControl {
+    /// Returns `true` if the next stream should be read.
+    fn should_continue(self) -> bool;
+}
+
+impl StreamControl for () {
+    fn should_continue(self) -> bool {
+        true
+    }
+}
+
+impl<B> StreamControl for ControlFlow<B> {
+    fn should_continue(self) -> bool {
+        self.is_continue()
+    }
+}
+
 /// Represents an error that can occur when reading sound bank streams with [`Bank::read_streams`].
 #[derive(Debug)]
 pub struct LazyStreamError<E> {
@@ -226,3 +595,640 @@ impl<E: Error + 'static> Error for LazyStreamError<E> {
         }
     }
 }
+
+/// Represents an error that can occur when reading sound bank streams with [`Bank::try_streams`].
+#[derive(Debug)]
+pub struct StreamReadError {
+    index: u32,
+    source: ReadError,
+}
+
+impl StreamReadError {
+    pub(crate) fn factory(index: u32) -> impl FnOnce(ReadError) -> Self {
+        move |source| Self { index, source }
+    }
+
+    /// Returns the index of the stream that could not be read.
+    #[must_use]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the kind of error that occurred while reading the stream data.
+    #[must_use]
+    pub fn kind(&self) -> ReadErrorKind {
+        self.source.kind()
+    }
+
+    /// Returns the byte position in the file where reading failed.
+    #[must_use]
+    pub fn position(&self) -> u64 {
+        self.source.position()
+    }
+}
+
+impl Display for StreamReadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_fmt(format_args!("failed to read stream at index {}", self.index))
+    }
+}
+
+impl Error for StreamReadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::Bank;
+    use crate::encode::{Container, EncodeOptions};
+    use crate::fixture::pcm16_headers;
+    use crate::header::{
+        error::{HeaderErrorKind, NameErrorKind},
+        options::{Limits, ParseOptions, ParseWarning},
+        BankFlags, Chunk, Loop,
+    };
+    use crate::read::ReadErrorKind;
+    use crate::validate::ValidationIssue;
+    use std::{
+        io::{Cursor, ErrorKind, Read, Result as IoResult, Seek, SeekFrom},
+        num::NonZeroU32,
+        ops::ControlFlow,
+        time::Duration,
+    };
+
+    // Builds a PCM16 sound bank with mono streams of the given sizes, each containing `size / 2` samples.
+    pub(crate) fn test_bank(sizes: &[u32]) -> Vec<u8> {
+        let mut data = pcm16_headers(sizes);
+
+        for (size, byte) in sizes.iter().zip(1..) {
+            data.resize(data.len() + *size as usize, byte);
+        }
+
+        data
+    }
+
+    #[test]
+    fn iterate_streams() {
+        let data = test_bank(&[32, 64]);
+
+        let bank = Bank::new(data.as_slice()).unwrap();
+        let streams: Vec<_> = bank.try_streams().map(Result::unwrap).collect();
+        assert_eq!(streams.len(), 2);
+
+        let bank = Bank::new(data.as_slice()).unwrap();
+        let mut streams = bank.into_iter();
+        assert_eq!(streams.len(), 2);
+        assert!(streams.next().is_some());
+        assert_eq!(streams.len(), 1);
+        assert!(streams.next().is_some());
+        assert_eq!(streams.len(), 0);
+        assert!(streams.next().is_none());
+    }
+
+    #[test]
+    fn iterate_truncated_streams() {
+        let mut data = test_bank(&[32, 64, 32]);
+        data.truncate(data.len() - 40);
+
+        let bank = Bank::new(data.as_slice()).unwrap();
+        let mut streams = bank.try_streams();
+        assert!(streams.next().is_some_and(|stream| stream.is_ok()));
+
+        let err = streams.next().unwrap().unwrap_err();
+        assert_eq!(err.index(), 1);
+        assert!(matches!(err.kind(), ReadErrorKind::Incomplete(_)));
+        assert_eq!(err.position(), data.len() as u64);
+
+        // iteration stops after an error
+        assert_eq!(streams.len(), 0);
+        assert!(streams.next().is_none());
+
+        let bank = Bank::new(data.as_slice()).unwrap();
+        assert_eq!(bank.into_iter().count(), 1);
+    }
+
+    #[test]
+    fn parse_leniently() {
+        let mut data = test_bank(&[32]);
+
+        // add a chunk of unknown type (0x7F) to the stream header
+        data[12..16].copy_from_slice(&16u32.to_le_bytes());
+        data[60] |= 1;
+        let chunk: u32 = 4 << 1 | 0x7F << 25;
+        drop(data.splice(68..68, chunk.to_le_bytes().into_iter().chain([0; 4])));
+
+        // add a name table containing a name that isn't valid UTF-8
+        data[16..20].copy_from_slice(&9u32.to_le_bytes());
+        drop(data.splice(76..76, 4u32.to_le_bytes().into_iter().chain(*b"\xFFbad\0")));
+
+        assert!(Bank::new(data.as_slice()).is_err());
+
+        let options = ParseOptions::default().strict(false);
+        let bank = Bank::new_with(data.as_slice(), &options).unwrap();
+
+        assert_eq!(
+            bank.warnings(),
+            [
+                ParseWarning::UnknownChunk {
+                    stream_index: 0,
+                    chunk_index: 0,
+                    flag: 0x7F
+                },
+                ParseWarning::LossyName { stream_index: 0 }
+            ]
+        );
+
+        let stream = bank.into_iter().next().unwrap();
+        assert_eq!(stream.name(), Some("\u{FFFD}bad"));
+    }
+
+    #[test]
+    fn keep_stream_chunks() {
+        let mut data = test_bank(&[32]);
+
+        // add a loop chunk followed by a chunk of unknown type (0x7F) to the stream header
+        data[12..16].copy_from_slice(&28u32.to_le_bytes());
+        data[60] |= 1;
+        let loop_chunk: u32 = 1 | 8 << 1 | 3 << 25;
+        let unknown_chunk: u32 = 4 << 1 | 0x7F << 25;
+        let chunks = [loop_chunk, 2, 5, unknown_chunk]
+            .into_iter()
+            .flat_map(u32::to_le_bytes);
+        drop(data.splice(68..68, chunks.chain([1, 2, 3, 4])));
+
+        let options = ParseOptions::default().strict(false);
+        let stream = Bank::new_with(data.as_slice(), &options)
+            .unwrap()
+            .into_iter()
+            .next()
+            .unwrap();
+
+        let stream_loop = Loop::parse(0, 2, 5).unwrap();
+        assert_eq!(stream.loop_info(), Some(stream_loop));
+        assert_eq!(
+            stream.chunks().collect::<Vec<_>>(),
+            [
+                Chunk::Loop(stream_loop),
+                Chunk::Raw {
+                    kind: 0x7F,
+                    data: &[1, 2, 3, 4]
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn read_peak_volume_and_comment() {
+        let mut data = test_bank(&[32]);
+
+        // add a comment chunk followed by a peak volume chunk to the stream header
+        data[12..16].copy_from_slice(&28u32.to_le_bytes());
+        data[60] |= 1;
+        let comment_chunk: u32 = 1 | 8 << 1 | 4 << 25;
+        let peak_volume_chunk: u32 = 4 << 1 | 13 << 25;
+        let chunks = comment_chunk
+            .to_le_bytes()
+            .into_iter()
+            .chain(*b"hello\0\0\0")
+            .chain(peak_volume_chunk.to_le_bytes())
+            .chain(0.5f32.to_le_bytes());
+        drop(data.splice(68..68, chunks));
+
+        let stream = Bank::new(data.as_slice()).unwrap().into_iter().next().unwrap();
+        assert_eq!(stream.comment(), Some("hello"));
+        assert_eq!(stream.peak_volume(), Some(0.5));
+
+        let bank = Bank::new(data.as_slice()).unwrap();
+        bank.read_streams(|stream| {
+            assert_eq!(stream.comment(), Some("hello"));
+            assert_eq!(stream.peak_volume(), Some(0.5));
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn keep_invalid_metadata_chunks() {
+        let mut data = test_bank(&[32]);
+
+        // add a comment that isn't valid UTF-8, followed by a peak volume that is too short
+        data[12..16].copy_from_slice(&22u32.to_le_bytes());
+        data[60] |= 1;
+        let comment_chunk: u32 = 1 | 4 << 1 | 4 << 25;
+        let peak_volume_chunk: u32 = 2 << 1 | 13 << 25;
+        let chunks = comment_chunk
+            .to_le_bytes()
+            .into_iter()
+            .chain([0xFF, 0xFE, 0, 0])
+            .chain(peak_volume_chunk.to_le_bytes())
+            .chain([0, 0]);
+        drop(data.splice(68..68, chunks));
+
+        for strict in [true, false] {
+            let options = ParseOptions::default().strict(strict);
+            let bank = Bank::new_with(data.as_slice(), &options).unwrap();
+            assert!(bank.warnings().is_empty());
+
+            let stream = bank.into_iter().next().unwrap();
+            assert_eq!(stream.comment(), None);
+            assert_eq!(stream.peak_volume(), None);
+            assert_eq!(
+                stream.chunks().collect::<Vec<_>>(),
+                [
+                    Chunk::Raw {
+                        kind: 4,
+                        data: &[0xFF, 0xFE, 0, 0]
+                    },
+                    Chunk::Raw {
+                        kind: 13,
+                        data: &[0, 0]
+                    }
+                ]
+            );
+            assert!(stream.write(Vec::new()).is_ok());
+        }
+    }
+
+    #[test]
+    fn read_streams_until_stopped() {
+        let data = test_bank(&[32, 64, 96]);
+
+        // state can be kept across calls without interior mutability
+        let mut sizes = Vec::new();
+        Bank::new(data.as_slice())
+            .unwrap()
+            .read_streams(|stream| {
+                sizes.push(stream.size().get());
+                Ok::<_, ()>(())
+            })
+            .unwrap();
+        assert_eq!(sizes, [32, 64, 96]);
+
+        let mut indices = Vec::new();
+        Bank::new(data.as_slice())
+            .unwrap()
+            .read_streams(|stream| {
+                indices.push(stream.index());
+                Ok::<_, ()>(if stream.index() == 1 {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                })
+            })
+            .unwrap();
+        assert_eq!(indices, [0, 1]);
+
+        // streams after the stopping point aren't read, so missing data isn't an error
+        Bank::new(&data[..data.len() - 1])
+            .unwrap()
+            .read_streams(|_| Ok::<_, ()>(ControlFlow::Break(())))
+            .unwrap();
+    }
+
+    #[test]
+    fn read_filtered_streams() {
+        let mut data = test_bank(&[32, 64, 96]);
+
+        // add a name table with three 4-byte names
+        let name_table = [12u32, 16, 20]
+            .into_iter()
+            .flat_map(u32::to_le_bytes)
+            .chain(*b"one\0two\0six\0");
+        data[16..20].copy_from_slice(&24u32.to_le_bytes());
+        drop(data.splice(84..84, name_table));
+
+        let mut written = Vec::new();
+        Bank::new(data.as_slice())
+            .unwrap()
+            .read_streams_filtered(
+                |stream| {
+                    stream.name().is_some_and(|name| name.starts_with('t')) || stream.index() == 2
+                },
+                |stream| {
+                    written.push((stream.index(), stream.write(Vec::new()).unwrap()));
+                    Ok::<_, ()>(())
+                },
+            )
+            .unwrap();
+
+        assert_eq!(written.len(), 2);
+        assert_eq!(written[0].0, 1);
+        assert_eq!(written[0].1.len(), 44 + 64);
+        assert!(written[0].1[44..].iter().all(|byte| *byte == 2));
+        assert_eq!(written[1].0, 2);
+        assert!(written[1].1[44..].iter().all(|byte| *byte == 3));
+
+        // skipped streams must still be present in the data
+        let err = Bank::new(&data[..data.len() - 100])
+            .unwrap()
+            .read_streams_filtered(|stream| stream.index() == 2, |_| Ok::<_, ()>(()))
+            .unwrap_err();
+        assert_eq!(err.index(), 1);
+    }
+
+    #[test]
+    fn read_bank_header() {
+        let mut data = test_bank(&[32, 64]);
+        data[32..36].copy_from_slice(&1u32.to_le_bytes());
+        data[36..52].copy_from_slice(&[0xAB; 16]);
+
+        let bank = Bank::new(data.as_slice()).unwrap();
+        let header = bank.header();
+
+        assert_eq!(header.version(), 1);
+        assert!(header.flags().contains(BankFlags::BIG_ENDIAN_PCM));
+        assert_eq!(header.flags().bits(), 1);
+        assert_eq!(header.hash(), [0xAB; 16]);
+        assert_eq!(header.stream_headers_size(), 16);
+        assert_eq!(header.name_table_size(), 0);
+        assert_eq!(header.total_stream_size().get(), 96);
+    }
+
+    #[test]
+    fn list_stream_infos() {
+        let data = test_bank(&[32, 64]);
+        let bank = Bank::new(data.as_slice()).unwrap();
+
+        let infos: Vec<_> = bank.stream_infos().collect();
+        assert_eq!(infos.len(), 2);
+
+        assert_eq!(infos[1].index(), 1);
+        assert_eq!(infos[1].name(), None);
+        assert_eq!(infos[1].sample_rate().get(), 44100);
+        assert_eq!(infos[1].channels().get(), 1);
+        assert_eq!(infos[1].sample_count().get(), 32);
+        assert_eq!(infos[1].loop_info(), None);
+        assert_eq!(infos[1].size().get(), 64);
+        assert_eq!(infos[1].duration(), Duration::from_nanos(725_623));
+
+        // stream data starts after the 60-byte base header and two 8-byte stream headers
+        assert_eq!(infos[0].data_offset(), 76);
+        assert_eq!(infos[1].data_offset(), 108);
+        assert_eq!(data[108], 2);
+    }
+
+    #[test]
+    fn locate_stream_data() {
+        let data = test_bank(&[32, 64]);
+
+        let streams: Vec<_> = Bank::new(data.as_slice()).unwrap().into_iter().collect();
+        assert_eq!(streams[1].data_offset(), 108);
+        assert_eq!(streams[1].data_range(), 108..172);
+        assert_eq!(streams[1].duration(), Duration::from_nanos(725_623));
+        assert!(data[108..].iter().all(|byte| *byte == 2));
+
+        let bank = Bank::new(data.as_slice()).unwrap();
+        assert_eq!(bank.stream_infos().next().unwrap().data_range(), 76..108);
+        bank.read_streams(|stream| {
+            assert_eq!(stream.data_range(), streams[stream.index() as usize].data_range());
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn parse_at_base_offset() {
+        let mut data = vec![0xAA; 100];
+        data.extend(test_bank(&[32, 64]));
+
+        let options = ParseOptions::default().base_offset(100);
+        let bank = Bank::new_with(data.as_slice(), &options).unwrap();
+        assert_eq!(bank.num_streams().get(), 2);
+
+        // data offsets are measured from the start of the source
+        let streams: Vec<_> = bank.into_iter().collect();
+        assert_eq!(streams[1].data_range(), 208..272);
+        assert!(data[208..].iter().all(|byte| *byte == 2));
+
+        // as are byte positions in errors
+        data.truncate(165);
+        let err = Bank::new_with(data.as_slice(), &options).unwrap_err();
+        assert_eq!(err.kind(), HeaderErrorKind::StreamHeader);
+        assert_eq!(err.position(), Some(165));
+
+        let err = Bank::new_with(&data[..50], &options).unwrap_err();
+        assert_eq!(err.kind(), HeaderErrorKind::Magic);
+        assert_eq!(err.position(), Some(50));
+    }
+
+    #[test]
+    fn parse_embedded_bank() {
+        let bank = test_bank(&[32, 64]);
+        let mut data = vec![0xAA; 100];
+        data.extend(&bank);
+        data.extend([0xBB; 50]);
+
+        let source = Cursor::new(data.as_slice());
+        let bank_len = bank.len() as u64;
+        let streams: Vec<_> = Bank::new_at(source, 100, bank_len).unwrap().into_iter().collect();
+        assert_eq!(streams.len(), 2);
+
+        // positions are relative to the start of the embedded sound bank
+        assert_eq!(streams[1].data_range(), 108..172);
+
+        // reading stops at the end of the sound bank, even if the source continues
+        let source = Cursor::new(data.as_slice());
+        let bank = Bank::new_at(source, 100, bank_len - 10).unwrap();
+        let err = bank.try_streams().nth(1).unwrap().unwrap_err();
+        assert_eq!(err.index(), 1);
+        assert!(matches!(err.kind(), ReadErrorKind::Incomplete(_)));
+        assert_eq!(err.position(), bank_len - 10);
+
+        // the base offset is replaced by the offset of the embedded sound bank
+        let source = Cursor::new(data.as_slice());
+        let options = ParseOptions::default().base_offset(100);
+        let bank = Bank::new_at_with(source, 100, bank_len, &options).unwrap();
+        assert_eq!(bank.stream_infos().nth(1).unwrap().data_offset(), 108);
+    }
+
+    #[test]
+    fn fail_to_seek_embedded_bank() {
+        #[derive(Debug)]
+        struct Unseekable;
+
+        impl Read for Unseekable {
+            fn read(&mut self, _buf: &mut [u8]) -> IoResult<usize> {
+                Ok(0)
+            }
+        }
+
+        impl Seek for Unseekable {
+            fn seek(&mut self, _pos: SeekFrom) -> IoResult<u64> {
+                Err(ErrorKind::Unsupported.into())
+            }
+        }
+
+        let err = Bank::new_at(Unseekable, 100, 200).unwrap_err();
+        assert_eq!(err.kind(), HeaderErrorKind::Seek);
+        assert_eq!(err.position(), Some(100));
+    }
+
+    #[test]
+    fn enforce_limits() {
+        let parse = |data: &[u8], limits| {
+            let options = ParseOptions::default().limits(limits);
+            Bank::new_with(data, &options).map(|_| ()).unwrap_err()
+        };
+
+        let mut data = test_bank(&[32, 64]);
+
+        let err = parse(&data, Limits::default().max_streams(1));
+        assert_eq!(
+            err.kind(),
+            HeaderErrorKind::TooManyStreams {
+                num_streams: 2,
+                limit: 1
+            }
+        );
+
+        let err = parse(&data, Limits::default().max_stream_size(32));
+        assert_eq!(err.stream_index(), Some(1));
+
+        let err = parse(&data, Limits::default().max_allocation(8));
+        assert_eq!(err.kind(), HeaderErrorKind::AllocationLimit { limit: 8 });
+
+        // add a name table with two 4-byte names
+        let name_table = [8u32, 12]
+            .into_iter()
+            .flat_map(u32::to_le_bytes)
+            .chain(*b"one\0two\0");
+        data[16..20].copy_from_slice(&16u32.to_le_bytes());
+        drop(data.splice(76..76, name_table));
+
+        let err = parse(&data, Limits::default().max_name_len(3));
+        assert_eq!(err.name_kind(), Some(NameErrorKind::TooLong { len: 4, limit: 3 }));
+
+        // metadata fits within the allocation limit, but stream data doesn't
+        let data = test_bank(&[1024]);
+        let options = ParseOptions::default().limits(Limits::default().max_allocation(512));
+        let err = Bank::new_with(data.as_slice(), &options)
+            .unwrap()
+            .try_streams()
+            .next()
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(
+            err.kind(),
+            ReadErrorKind::AllocationLimit {
+                requested: 1024,
+                limit: 512
+            }
+        );
+    }
+
+    #[test]
+    fn parse_unordered_name_offsets() {
+        let with_name_offsets = |offsets: [u32; 2]| {
+            let mut data = test_bank(&[32, 64]);
+            let name_table = offsets.into_iter().flat_map(u32::to_le_bytes).chain(*b"one\0two\0");
+            data[16..20].copy_from_slice(&16u32.to_le_bytes());
+            drop(data.splice(76..76, name_table));
+            data
+        };
+
+        let options = ParseOptions::default().strict(false);
+
+        // the first offset is greater than the second offset
+        let data = with_name_offsets([100, 0]);
+        let err = Bank::new_with(data.as_slice(), &options).unwrap_err();
+        assert_eq!(err.kind(), HeaderErrorKind::NameTable);
+        assert_eq!(err.name_kind(), Some(NameErrorKind::UnorderedNameOffset));
+        assert_eq!(err.stream_index(), Some(0));
+
+        // the last offset is greater than the size of the name table
+        let data = with_name_offsets([8, 20]);
+        let err = Bank::new(data.as_slice()).unwrap_err();
+        assert_eq!(err.name_kind(), Some(NameErrorKind::UnorderedNameOffset));
+        assert_eq!(err.stream_index(), Some(1));
+    }
+
+    fn set_stream_header(data: &mut [u8], index: usize, f: impl FnOnce(u64) -> u64) {
+        let range = 60 + index * 8..68 + index * 8;
+        let header = u64::from_le_bytes(data[range.clone()].try_into().unwrap());
+        data[range].copy_from_slice(&f(header).to_le_bytes());
+    }
+
+    #[test]
+    fn validate_bank() {
+        let mut data = test_bank(&[32, 64]);
+        assert!(Bank::new(data.as_slice()).unwrap().validate().is_valid());
+
+        // the first stream claims to have more samples than its data contains
+        set_stream_header(&mut data, 0, |header| header & ((1 << 34) - 1) | 100 << 34);
+
+        // the name table contains an offset that doesn't account for the offset itself
+        let name_table = [0u32, 5].into_iter().flat_map(u32::to_le_bytes).chain(*b"one\0two\0");
+        data[16..20].copy_from_slice(&16u32.to_le_bytes());
+        drop(data.splice(76..76, name_table));
+
+        let report = Bank::new(data.as_slice()).unwrap().validate();
+        assert_eq!(
+            report.issues(),
+            [
+                ValidationIssue::ImplausibleSampleCount {
+                    index: 0,
+                    num_samples: NonZeroU32::new(100).unwrap(),
+                    size: NonZeroU32::new(32).unwrap()
+                },
+                ValidationIssue::NameTableNotConsumed {
+                    expected: 16,
+                    actual: 24
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn skip_unused_name_table_space() {
+        let mut data = test_bank(&[32, 64]);
+
+        // the names only take up 16 of the 20 bytes of the name table
+        let name_table = [12u32, 16]
+            .into_iter()
+            .flat_map(u32::to_le_bytes)
+            .chain(*b"one\0two\0\0\0\0\0");
+        data[16..20].copy_from_slice(&20u32.to_le_bytes());
+        drop(data.splice(76..76, name_table));
+
+        let bank = Bank::new(data.as_slice()).unwrap();
+        assert_eq!(
+            bank.validate().issues(),
+            [ValidationIssue::NameTableNotConsumed {
+                expected: 20,
+                actual: 16
+            }]
+        );
+
+        // stream data starts after the whole name table
+        let mut streams = bank.into_iter();
+        let first = streams.next().unwrap();
+        let second = streams.next().unwrap();
+        assert_eq!(first.data_range(), 96..128);
+        assert_eq!(second.data_range(), 128..192);
+
+        let options = EncodeOptions::default().container(Container::Raw);
+        assert_eq!(first.write_with(Vec::new(), &options).unwrap(), [1; 32]);
+        assert_eq!(second.write_with(Vec::new(), &options).unwrap(), [2; 64]);
+    }
+
+    #[test]
+    fn parse_unordered_offsets() {
+        let mut data = test_bank(&[32, 64]);
+        set_stream_header(&mut data, 0, |header| header | 2 << 7);
+
+        assert!(Bank::new(data.as_slice())
+            .is_err_and(|e| e.kind() == HeaderErrorKind::UnorderedStreamOffset { index: 1 }));
+
+        // stream sizes depend on the order of offsets, so this isn't recovered from in non-strict mode
+        let options = ParseOptions::default().strict(false);
+        assert!(Bank::new_with(data.as_slice(), &options)
+            .is_err_and(|e| e.kind() == HeaderErrorKind::UnorderedStreamOffset { index: 1 }));
+
+        let mut data = test_bank(&[32, 64]);
+        set_stream_header(&mut data, 0, |header| header | 1 << 7);
+        assert!(Bank::new_with(data.as_slice(), &options)
+            .is_err_and(|e| e.kind() == HeaderErrorKind::ZeroStreamSize { index: 0 }));
+    }
+}
@@ -0,0 +1,196 @@
+use crate::template::Template;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use fsbex::{encode::Container, AudioFormat, StreamView};
+use glob::Pattern;
+use std::path::PathBuf;
+
+/// Extract audio from FMOD sound banks
+#[derive(Debug, Parser)]
+#[command(name = "fsbex", version)]
+pub(crate) struct Cli {
+    #[command(subcommand)]
+    pub(crate) command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum Command {
+    /// Show information from the file headers of sound banks
+    Info {
+        #[command(flatten)]
+        input: InputArgs,
+        /// Print information as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// List the streams in sound banks
+    List {
+        #[command(flatten)]
+        input: InputArgs,
+        #[command(flatten)]
+        selection: Selection,
+        /// Print streams as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Write streams of sound banks to audio files
+    Extract {
+        #[command(flatten)]
+        input: InputArgs,
+        #[command(flatten)]
+        selection: Selection,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Check sound banks for inconsistencies
+    Validate {
+        #[command(flatten)]
+        input: InputArgs,
+    },
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct InputArgs {
+    /// Sound bank files; glob patterns such as `banks/*.fsb` are expanded
+    #[arg(required = true, value_name = "INPUT")]
+    pub(crate) inputs: Vec<String>,
+    /// Only read sound banks with these audio formats (e.g. `vorbis`, `pcm16`)
+    #[arg(long = "format", value_name = "FORMAT", value_parser = parse_format, value_delimiter = ',')]
+    pub(crate) formats: Vec<AudioFormat>,
+}
+
+impl InputArgs {
+    pub(crate) fn accepts(&self, format: AudioFormat) -> bool {
+        self.formats.is_empty() || self.formats.contains(&format)
+    }
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct Selection {
+    /// Only select streams with these indices
+    #[arg(short, long = "index", value_name = "INDEX", value_delimiter = ',')]
+    indices: Vec<u32>,
+    /// Only select streams whose names match these glob patterns (e.g. `music_*`)
+    #[arg(short, long = "name", value_name = "PATTERN")]
+    names: Vec<Pattern>,
+}
+
+impl Selection {
+    // Streams are selected if they match any index or name, or if no indices or names were given.
+    pub(crate) fn matches(&self, stream: StreamView<'_>) -> bool {
+        if self.indices.is_empty() && self.names.is_empty() {
+            return true;
+        }
+
+        self.indices.contains(&stream.index())
+            || stream
+                .name()
+                .is_some_and(|name| self.names.iter().any(|pattern| pattern.matches(name)))
+    }
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct OutputArgs {
+    /// Directory to write audio files to
+    #[arg(short = 'o', long = "output", value_name = "DIR", default_value = ".")]
+    pub(crate) dir: PathBuf,
+    /// File name template, with placeholders `{bank}`, `{index}` (or `{index:03}`) and `{name}`;
+    /// the file extension is added automatically
+    #[arg(short, long, default_value = "{bank}/{name}")]
+    pub(crate) template: Template,
+    /// Container to write audio in [default: Ogg for Vorbis streams, WAVE otherwise]
+    #[arg(short, long, value_enum)]
+    pub(crate) container: Option<ContainerArg>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum ContainerArg {
+    /// WAVE file
+    Wav,
+    /// Ogg Vorbis file
+    Ogg,
+    /// FLAC file
+    Flac,
+    /// Headerless PCM
+    Raw,
+}
+
+impl ContainerArg {
+    pub(crate) fn container(self) -> Container {
+        match self {
+            Self::Wav => Container::Wav,
+            Self::Ogg => Container::Ogg,
+            Self::Flac => Container::Flac,
+            Self::Raw => Container::Raw,
+        }
+    }
+
+    pub(crate) fn extension(container: Option<Self>, format: AudioFormat) -> &'static str {
+        match container {
+            Some(Self::Ogg) => "ogg",
+            Some(Self::Flac) => "flac",
+            Some(Self::Raw) => "pcm",
+            None if format == AudioFormat::Vorbis => "ogg",
+            Some(Self::Wav) | None => "wav",
+        }
+    }
+}
+
+const FORMAT_NAMES: [(&str, AudioFormat); 17] = [
+    ("pcm8", AudioFormat::Pcm8),
+    ("pcm16", AudioFormat::Pcm16),
+    ("pcm24", AudioFormat::Pcm24),
+    ("pcm32", AudioFormat::Pcm32),
+    ("pcmfloat", AudioFormat::PcmFloat),
+    ("gcadpcm", AudioFormat::GcAdpcm),
+    ("imaadpcm", AudioFormat::ImaAdpcm),
+    ("vag", AudioFormat::Vag),
+    ("hevag", AudioFormat::HeVag),
+    ("xma", AudioFormat::Xma),
+    ("mpeg", AudioFormat::Mpeg),
+    ("celt", AudioFormat::Celt),
+    ("atrac9", AudioFormat::Atrac9),
+    ("xwma", AudioFormat::Xwma),
+    ("vorbis", AudioFormat::Vorbis),
+    ("fadpcm", AudioFormat::FAdpcm),
+    ("opus", AudioFormat::Opus),
+];
+
+// the name of a format as accepted by `--format`, also used in JSON output
+pub(crate) fn format_name(format: AudioFormat) -> &'static str {
+    FORMAT_NAMES
+        .iter()
+        .find(|(_, known)| *known == format)
+        .map_or("unknown", |(name, _)| name)
+}
+
+fn parse_format(value: &str) -> Result<AudioFormat, String> {
+    FORMAT_NAMES
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(value))
+        .map(|(_, format)| *format)
+        .ok_or_else(|| {
+            let names: Vec<_> = FORMAT_NAMES.iter().map(|(name, _)| *name).collect();
+            format!("expected one of: {}", names.join(", "))
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{format_name, parse_format, Cli};
+    use clap::CommandFactory;
+    use fsbex::AudioFormat;
+
+    #[test]
+    fn verify_arguments() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn parse_format_names() {
+        assert_eq!(parse_format("vorbis"), Ok(AudioFormat::Vorbis));
+        assert_eq!(parse_format("PCM16"), Ok(AudioFormat::Pcm16));
+        assert!(parse_format("mp3").is_err());
+
+        assert_eq!(format_name(AudioFormat::HeVag), "hevag");
+    }
+}
@@ -0,0 +1,316 @@
+//! Command-line tool for inspecting FMOD sound banks and extracting their streams as audio files.
+
+// The binary doesn't use most of the library's dependencies directly.
+#![allow(unused_crate_dependencies)]
+
+mod args;
+mod template;
+
+use args::{format_name, Cli, Command, ContainerArg, InputArgs, OutputArgs, Selection};
+use clap::Parser;
+use fsbex::{encode::EncodeOptions, Bank, LazyStream, StreamView};
+use serde_json::{json, Value};
+use std::{
+    convert::Infallible,
+    error::Error,
+    fmt::Write as _,
+    fs::{self, File},
+    io::{BufReader, BufWriter, Error as IoError, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+type FileBank = Bank<BufReader<File>>;
+
+fn main() -> ExitCode {
+    let succeeded = match Cli::parse().command {
+        Command::Info { input, json } => info(&input, json),
+        Command::List {
+            input,
+            selection,
+            json,
+        } => list(&input, &selection, json),
+        Command::Extract {
+            input,
+            selection,
+            output,
+        } => extract(&input, &selection, &output),
+        Command::Validate { input } => validate(&input),
+    };
+
+    if succeeded {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+// Errors are reported as they occur, and the remaining inputs are still processed.
+// Each command returns whether every input was processed successfully.
+
+fn info(input: &InputArgs, json: bool) -> bool {
+    let mut banks = Vec::new();
+    let succeeded = for_each_bank(input, |path, bank| {
+        let header = bank.header();
+
+        if json {
+            banks.push(json!({
+                "path": path.display().to_string(),
+                "format": format_name(bank.format()),
+                "num_streams": bank.num_streams().get(),
+                "version": header.version(),
+                "flags": header.flags().bits(),
+                "hash": hex(&header.hash()),
+                "stream_headers_size": header.stream_headers_size(),
+                "name_table_size": header.name_table_size(),
+                "total_stream_size": header.total_stream_size().get(),
+            }));
+        } else {
+            println!("{}", path.display());
+            println!("  format:              {}", bank.format());
+            println!("  streams:             {}", bank.num_streams());
+            println!("  version:             {}", header.version());
+            println!("  flags:               {:#x}", header.flags().bits());
+            println!("  hash:                {}", hex(&header.hash()));
+            println!("  stream headers size: {}", header.stream_headers_size());
+            println!("  name table size:     {}", header.name_table_size());
+            println!("  stream data size:    {}", header.total_stream_size());
+        }
+
+        true
+    });
+
+    if json {
+        print_json(&Value::Array(banks));
+    }
+
+    succeeded
+}
+
+fn list(input: &InputArgs, selection: &Selection, json: bool) -> bool {
+    let mut banks = Vec::new();
+    let succeeded = for_each_bank(input, |path, bank| {
+        let streams = bank.stream_infos().filter(|stream| selection.matches(*stream));
+
+        if json {
+            banks.push(json!({
+                "path": path.display().to_string(),
+                "format": format_name(bank.format()),
+                "streams": streams.map(stream_json).collect::<Vec<_>>(),
+            }));
+        } else {
+            println!(
+                "{} ({}, {} streams)",
+                path.display(),
+                bank.format(),
+                bank.num_streams()
+            );
+
+            for stream in streams {
+                print!(
+                    "  {:>5}  {}  {} Hz, {} ch, {:.3} s, {} bytes",
+                    stream.index(),
+                    stream.name().unwrap_or("-"),
+                    stream.sample_rate(),
+                    stream.channels(),
+                    stream.duration().as_secs_f64(),
+                    stream.size(),
+                );
+
+                match stream.loop_info() {
+                    Some(stream_loop) => {
+                        println!(", loop {}..{}", stream_loop.start(), stream_loop.end());
+                    }
+                    None => println!(),
+                }
+            }
+        }
+
+        true
+    });
+
+    if json {
+        print_json(&Value::Array(banks));
+    }
+
+    succeeded
+}
+
+fn extract(input: &InputArgs, selection: &Selection, output: &OutputArgs) -> bool {
+    let mut options = EncodeOptions::default();
+    if let Some(container) = output.container {
+        options = options.container(container.container());
+    }
+
+    for_each_bank(input, |path, bank| {
+        let bank_name = path
+            .file_stem()
+            .map_or_else(|| "bank".into(), |stem| stem.to_string_lossy());
+        let extension = ContainerArg::extension(output.container, bank.format());
+        let mut succeeded = true;
+
+        let result = bank.read_streams_filtered(
+            |stream| selection.matches(stream),
+            |stream| {
+                let file_name = output.template.render(&bank_name, stream.index(), stream.name());
+                let out_path = output.dir.join(format!("{file_name}.{extension}"));
+
+                match write_stream(stream, &out_path, &options) {
+                    Ok(()) => println!("{}", out_path.display()),
+                    Err(e) => {
+                        report(&out_path, &*e);
+                        succeeded = false;
+                    }
+                }
+
+                Ok::<_, Infallible>(())
+            },
+        );
+
+        if let Err(e) = result {
+            report(path, &e);
+            succeeded = false;
+        }
+
+        succeeded
+    })
+}
+
+fn validate(input: &InputArgs) -> bool {
+    for_each_bank(input, |path, bank| {
+        let report = bank.validate();
+
+        if report.is_valid() {
+            println!("{}: {report}", path.display());
+        } else {
+            println!("{}:", path.display());
+            for issue in report.issues() {
+                println!("  {issue}");
+            }
+        }
+
+        report.is_valid()
+    })
+}
+
+// Opens every sound bank given as input whose format is accepted, and calls `f` with it.
+fn for_each_bank(input: &InputArgs, mut f: impl FnMut(&Path, FileBank) -> bool) -> bool {
+    let mut succeeded = true;
+
+    for pattern in &input.inputs {
+        let paths = match expand(pattern) {
+            Ok(paths) => paths,
+            Err(e) => {
+                eprintln!("fsbex: {e}");
+                succeeded = false;
+                continue;
+            }
+        };
+
+        for path in paths {
+            match open(&path) {
+                Ok(bank) if input.accepts(bank.format()) => succeeded &= f(&path, bank),
+                Ok(_) => {}
+                Err(e) => {
+                    report(&path, &*e);
+                    succeeded = false;
+                }
+            }
+        }
+    }
+
+    succeeded
+}
+
+// Shells on some platforms don't expand glob patterns, so they are expanded here.
+// Inputs that aren't valid patterns (e.g. containing unmatched brackets) are treated as file paths.
+fn expand(pattern: &str) -> Result<Vec<PathBuf>, String> {
+    let Ok(entries) = glob::glob(pattern) else {
+        return Ok(vec![PathBuf::from(pattern)]);
+    };
+
+    let mut paths: Vec<_> = entries.filter_map(Result::ok).filter(|path| path.is_file()).collect();
+
+    if paths.is_empty() {
+        if Path::new(pattern).exists() {
+            paths.push(PathBuf::from(pattern));
+        } else {
+            return Err(format!("{pattern}: no files found"));
+        }
+    }
+
+    Ok(paths)
+}
+
+fn open(path: &Path) -> Result<FileBank, Box<dyn Error>> {
+    let file = BufReader::new(File::open(path)?);
+    Ok(Bank::new(file)?)
+}
+
+// Partially written files are removed if encoding fails.
+fn write_stream(
+    stream: LazyStream<'_, BufReader<File>>,
+    path: &Path,
+    options: &EncodeOptions,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let file = BufWriter::new(File::create(path)?);
+
+    let result = stream
+        .write_with(file, options)
+        .map_err(Box::<dyn Error>::from)
+        .and_then(|mut file| file.flush().map_err(IoError::into));
+
+    if result.is_err() {
+        drop(fs::remove_file(path));
+    }
+
+    result
+}
+
+fn stream_json(stream: StreamView<'_>) -> Value {
+    json!({
+        "index": stream.index(),
+        "name": stream.name(),
+        "sample_rate": stream.sample_rate().get(),
+        "channels": stream.channels().get(),
+        "sample_count": stream.sample_count().get(),
+        "duration": stream.duration().as_secs_f64(),
+        "size": stream.size().get(),
+        "data_offset": stream.data_offset(),
+        "loop": stream.loop_info().map(|stream_loop| json!({
+            "start": stream_loop.start(),
+            "end": stream_loop.end().get(),
+        })),
+    })
+}
+
+fn print_json(value: &Value) {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(value).expect("JSON values can always be serialized")
+    );
+}
+
+fn report(path: &Path, error: &dyn Error) {
+    let mut message = format!("fsbex: {}: {error}", path.display());
+
+    let mut source = error.source();
+    while let Some(e) = source {
+        message.push_str(": ");
+        message.push_str(&e.to_string());
+        source = e.source();
+    }
+
+    eprintln!("{message}");
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut output, byte| {
+        let _ = write!(output, "{byte:02x}");
+        output
+    })
+}
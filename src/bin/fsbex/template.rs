@@ -0,0 +1,141 @@
+use std::{fmt::Write, str::FromStr};
+
+/// A file name template for extracted streams, such as `{bank}/{index:03}_{name}`.
+///
+/// Placeholders are:
+/// - `{bank}`: the file name of the sound bank, without its extension
+/// - `{index}`: the index of the stream, optionally zero-padded to a width (e.g. `{index:03}`)
+/// - `{name}`: the name of the stream, or `stream_{index}` if it has no name
+///
+/// Characters that aren't allowed in file names are replaced in the values of placeholders,
+/// so only the literal parts of a template can create subdirectories.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Bank,
+    Index { width: usize },
+    Name,
+}
+
+impl Template {
+    pub(crate) fn render(&self, bank: &str, index: u32, name: Option<&str>) -> String {
+        let mut output = String::new();
+
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => output.push_str(text),
+                Part::Bank => push_sanitized(&mut output, bank),
+                Part::Index { width } => {
+                    let _ = write!(output, "{index:0width$}");
+                }
+                Part::Name => match name {
+                    Some(name) => push_sanitized(&mut output, name),
+                    None => {
+                        let _ = write!(output, "stream_{index}");
+                    }
+                },
+            }
+        }
+
+        output
+    }
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut rest = s;
+
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(Part::Literal(rest[..start].to_owned()));
+            }
+
+            let Some(len) = rest[start..].find('}') else {
+                return Err(format!("unclosed placeholder in `{s}`"));
+            };
+            let placeholder = &rest[start + 1..start + len];
+
+            parts.push(match placeholder.split_once(':') {
+                None if placeholder == "bank" => Part::Bank,
+                None if placeholder == "name" => Part::Name,
+                None if placeholder == "index" => Part::Index { width: 0 },
+                Some(("index", width)) => width
+                    .parse()
+                    .map(|width| Part::Index { width })
+                    .map_err(|_| format!("invalid width `{width}` for `{{index}}`"))?,
+                _ => return Err(format!("unknown placeholder `{{{placeholder}}}`")),
+            });
+
+            rest = &rest[start + len + 1..];
+        }
+
+        if !rest.is_empty() {
+            parts.push(Part::Literal(rest.to_owned()));
+        }
+
+        if parts.is_empty() {
+            return Err("template must not be empty".to_owned());
+        }
+
+        Ok(Self { parts })
+    }
+}
+
+// Stream names come from the sound bank and can contain path separators,
+// which would otherwise write files outside of the output directory.
+fn push_sanitized(output: &mut String, value: &str) {
+    let start = output.len();
+
+    output.extend(value.chars().map(|c| match c {
+        '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+        c if c.is_control() => '_',
+        c => c,
+    }));
+
+    if matches!(&output[start..], "" | "." | "..") {
+        output.truncate(start);
+        output.push('_');
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Template;
+
+    #[test]
+    fn render_placeholders() {
+        let template: Template = "{bank}/{index:03}_{name}".parse().unwrap();
+
+        assert_eq!(template.render("music", 7, Some("theme")), "music/007_theme");
+        assert_eq!(template.render("music", 1234, None), "music/1234_stream_1234");
+
+        let template: Template = "{name}".parse().unwrap();
+        assert_eq!(template.render("music", 2, None), "stream_2");
+    }
+
+    #[test]
+    fn sanitize_values() {
+        let template: Template = "out/{bank}/{name}".parse().unwrap();
+
+        assert_eq!(template.render("a:b", 0, Some("../x/y")), "out/a_b/.._x_y");
+        assert_eq!(template.render("bank", 0, Some("..")), "out/bank/_");
+        assert_eq!(template.render("bank", 0, Some("tab\there")), "out/bank/tab_here");
+    }
+
+    #[test]
+    fn reject_invalid_templates() {
+        assert!("".parse::<Template>().is_err());
+        assert!("{bank".parse::<Template>().is_err());
+        assert!("{size}".parse::<Template>().is_err());
+        assert!("{index:x}".parse::<Template>().is_err());
+        assert!("{name:3}".parse::<Template>().is_err());
+    }
+}
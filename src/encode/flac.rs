@@ -0,0 +1,1022 @@
+use super::options::EncodeOptions;
+use super::pcm::{quantize, Dither};
+use super::{Decode, EncodeError};
+use crate::header::{Loop, StreamInfo};
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    io::{Error as IoError, Write},
+};
+
+// FLAC format information taken from:
+// [1]: https://www.rfc-editor.org/rfc/rfc9639.html
+// [2]: https://xiph.org/flac/format.html
+
+pub(super) fn encode<D: Decode, W: Write>(
+    bits_per_sample: u8,
+    mut dither: Option<Dither>,
+    info: &StreamInfo,
+    stream_loop: Option<Loop>,
+    options: &EncodeOptions,
+    mut decoder: D,
+    mut sink: W,
+) -> Result<W, EncodeError> {
+    let channels = usize::from(info.channels.get());
+    let sample_rate = info.sample_rate.get();
+
+    if channels > MAX_CHANNELS {
+        return Err(FlacError::new(FlacErrorKind::TooManyChannels).into());
+    }
+
+    if sample_rate > MAX_SAMPLE_RATE {
+        return Err(FlacError::new(FlacErrorKind::UnsupportedSampleRate).into());
+    }
+
+    write_metadata(bits_per_sample, info, stream_loop, options, &mut sink)
+        .map_err(FlacError::from_io(FlacErrorKind::CreateHeader))?;
+
+    let mut encoder = FrameEncoder {
+        bits_per_sample: bits_per_sample.into(),
+        sample_rate,
+        frame_number: 0,
+    };
+
+    // Exactly as many samples as the stream header specifies are written: extra samples are discarded,
+    // and silence is added if the decoder runs out of samples early.
+    let num_samples = info.num_samples.get() as usize;
+    let mut pending = vec![Vec::with_capacity(BLOCK_SIZE); channels];
+    let mut written = 0;
+    let mut received = 0;
+
+    loop {
+        let block = if received < num_samples {
+            decoder.next_block()?
+        } else {
+            None
+        };
+
+        if let Some(block) = block {
+            let block_len = block.first().map_or(0, Vec::len).min(num_samples - received);
+
+            for index in 0..block_len {
+                for (channel, samples) in pending.iter_mut().zip(&block) {
+                    let noise = dither.as_mut().map_or(0.0, Dither::next);
+                    channel.push(quantize(samples[index].into(), bits_per_sample.into(), noise));
+                }
+            }
+
+            received += block_len;
+        } else {
+            // pad the output with silence if the stream ended early
+            for channel in &mut pending {
+                channel.resize(channel.len() + num_samples - received, 0);
+            }
+
+            received = num_samples;
+        }
+
+        while pending[0].len() >= BLOCK_SIZE || (received == num_samples && !pending[0].is_empty())
+        {
+            let frame_len = pending[0].len().min(BLOCK_SIZE);
+
+            let frame = encoder.encode(&pending, frame_len);
+            sink.write_all(&frame)
+                .map_err(FlacError::from_io(FlacErrorKind::EncodeFrame))?;
+
+            for channel in &mut pending {
+                drop(channel.drain(..frame_len));
+            }
+
+            written += frame_len;
+        }
+
+        if written == num_samples {
+            break;
+        }
+    }
+
+    sink.flush()
+        .map(|()| sink)
+        .map_err(FlacError::from_io(FlacErrorKind::FinishStream))
+        .map_err(Into::into)
+}
+
+// number of samples per channel in each frame (except the last)
+const BLOCK_SIZE: usize = 4096;
+
+const MAX_CHANNELS: usize = 8;
+
+// sample rates are stored as 20-bit integers
+const MAX_SAMPLE_RATE: u32 = (1 << 20) - 1;
+
+fn write_metadata<W: Write>(
+    bits_per_sample: u8,
+    info: &StreamInfo,
+    stream_loop: Option<Loop>,
+    options: &EncodeOptions,
+    sink: &mut W,
+) -> Result<(), IoError> {
+    const STREAMINFO: u8 = 0;
+    const VORBIS_COMMENT: u8 = 4;
+
+    sink.write_all(b"fLaC")?;
+
+    // The frame sizes and MD5 checksum of samples are optional, so they are set to 0 (unknown).
+    let mut stream_info = BitWriter::new();
+    stream_info.write(BLOCK_SIZE as u64, 16);
+    stream_info.write(BLOCK_SIZE as u64, 16);
+    stream_info.write(0, 24);
+    stream_info.write(0, 24);
+    stream_info.write(info.sample_rate.get().into(), 20);
+    stream_info.write(u64::from(info.channels.get()) - 1, 3);
+    stream_info.write(u64::from(bits_per_sample) - 1, 5);
+    stream_info.write(0, 4);
+    stream_info.write(info.num_samples.get().into(), 32);
+    let mut stream_info = stream_info.finish();
+    stream_info.extend_from_slice(&[0; 16]);
+
+    write_metadata_block(STREAMINFO, false, &stream_info, sink)?;
+
+    // Loop points are stored as comments, the same way as in Ogg Vorbis output.
+    let loop_comments = stream_loop.map(|stream_loop| {
+        [
+            format!("LOOPSTART={}", stream_loop.start()),
+            format!("LOOPLENGTH={}", stream_loop.len()),
+        ]
+    });
+    let comments: Vec<_> = loop_comments
+        .into_iter()
+        .flatten()
+        .chain(options.comments.iter().map(|(tag, value)| format!("{tag}={value}")))
+        .collect();
+
+    let mut vorbis_comment = Vec::new();
+    write_comment_string(VENDOR, &mut vorbis_comment)?;
+    vorbis_comment.write_all(&comment_len(comments.len())?.to_le_bytes())?;
+    for comment in &comments {
+        write_comment_string(comment, &mut vorbis_comment)?;
+    }
+
+    write_metadata_block(VORBIS_COMMENT, true, &vorbis_comment, sink)
+}
+
+const VENDOR: &str = concat!("fsbex ", env!("CARGO_PKG_VERSION"));
+
+fn write_metadata_block<W: Write>(
+    kind: u8,
+    is_last: bool,
+    data: &[u8],
+    sink: &mut W,
+) -> Result<(), IoError> {
+    // block lengths are stored as 24-bit integers
+    let len = u32::try_from(data.len())
+        .ok()
+        .filter(|len| *len < 1 << 24)
+        .ok_or_else(|| IoError::other("FLAC metadata block was too large"))?;
+
+    sink.write_all(&[u8::from(is_last) << 7 | kind])?;
+    sink.write_all(&len.to_be_bytes()[1..])?;
+    sink.write_all(data)
+}
+
+fn write_comment_string<W: Write>(comment: &str, sink: &mut W) -> Result<(), IoError> {
+    sink.write_all(&comment_len(comment.len())?.to_le_bytes())?;
+    sink.write_all(comment.as_bytes())
+}
+
+fn comment_len(len: usize) -> Result<u32, IoError> {
+    u32::try_from(len).map_err(|_| IoError::other("FLAC comment was too large"))
+}
+
+struct FrameEncoder {
+    bits_per_sample: u32,
+    sample_rate: u32,
+    frame_number: u32,
+}
+
+impl FrameEncoder {
+    // Encodes the first `len` samples of each channel as a single frame.
+    fn encode(&mut self, channels: &[Vec<i32>], len: usize) -> Vec<u8> {
+        let bps = self.bits_per_sample;
+        let channels: Vec<Vec<i64>> = channels
+            .iter()
+            .map(|channel| channel[..len].iter().copied().map(i64::from).collect())
+            .collect();
+
+        // Stereo streams are decorrelated by also trying combinations of the mid and side channels.
+        // The side channel needs an extra bit per sample.
+        let (side, mid);
+        let (assignment, subframes) = if let [left, right] = channels.as_slice() {
+            side = zip_with(left, right, |l, r| l - r);
+            mid = zip_with(left, right, |l, r| (l + r) >> 1);
+
+            let left = Subframe::encode(left, bps);
+            let right = Subframe::encode(right, bps);
+            let side = Subframe::encode(&side, bps + 1);
+            let mid = Subframe::encode(&mid, bps);
+
+            [
+                (ChannelAssignment::Independent, [&left, &right]),
+                (ChannelAssignment::LeftSide, [&left, &side]),
+                (ChannelAssignment::SideRight, [&side, &right]),
+                (ChannelAssignment::MidSide, [&mid, &side]),
+            ]
+            .into_iter()
+            .min_by_key(|(_, subframes)| subframes.iter().map(|s| s.bits).sum::<u64>())
+            .map(|(assignment, subframes)| (assignment, subframes.map(Clone::clone).to_vec()))
+            .expect("there are multiple channel assignments to choose from")
+        } else {
+            let subframes = channels.iter().map(|c| Subframe::encode(c, bps)).collect();
+            (ChannelAssignment::Independent, subframes)
+        };
+
+        let mut writer = BitWriter::new();
+
+        // frame header
+        writer.write(0b11_1111_1111_1110, 14);
+        writer.write(0, 1); // reserved
+        writer.write(0, 1); // fixed block size
+
+        let (block_size_code, block_size_extra) = block_size_code(len);
+        let (sample_rate_code, sample_rate_extra) = sample_rate_code(self.sample_rate);
+
+        writer.write(block_size_code, 4);
+        writer.write(sample_rate_code, 4);
+        writer.write(assignment.code(channels.len()), 4);
+        writer.write(sample_size_code(bps), 3);
+        writer.write(0, 1); // reserved
+        writer.write_utf8(self.frame_number);
+
+        if let Some((value, bits)) = block_size_extra {
+            writer.write(value, bits);
+        }
+        if let Some((value, bits)) = sample_rate_extra {
+            writer.write(value, bits);
+        }
+
+        let crc8 = crc8(writer.bytes());
+        writer.write(crc8.into(), 8);
+
+        for subframe in &subframes {
+            subframe.write(&mut writer);
+        }
+
+        writer.align();
+        let crc16 = crc16(writer.bytes());
+        writer.write(crc16.into(), 16);
+
+        self.frame_number += 1;
+        writer.finish()
+    }
+}
+
+fn zip_with(a: &[i64], b: &[i64], f: impl Fn(i64, i64) -> i64) -> Vec<i64> {
+    a.iter().zip(b).map(|(a, b)| f(*a, *b)).collect()
+}
+
+#[derive(Clone, Copy)]
+enum ChannelAssignment {
+    Independent,
+    LeftSide,
+    SideRight,
+    MidSide,
+}
+
+impl ChannelAssignment {
+    fn code(self, channels: usize) -> u64 {
+        match self {
+            Self::Independent => channels as u64 - 1,
+            Self::LeftSide => 0b1000,
+            Self::SideRight => 0b1001,
+            Self::MidSide => 0b1010,
+        }
+    }
+}
+
+fn block_size_code(len: usize) -> (u64, Option<(u64, u32)>) {
+    match len {
+        192 => (1, None),
+        576 | 1152 | 2304 | 4608 => (2 + u64::from((len / 576).trailing_zeros()), None),
+        256 | 512 | 1024 | 2048 | 4096 | 8192 | 16384 | 32768 => {
+            (8 + u64::from((len / 256).trailing_zeros()), None)
+        }
+        len if len <= 256 => (6, Some((len as u64 - 1, 8))),
+        len => (7, Some((len as u64 - 1, 16))),
+    }
+}
+
+fn sample_rate_code(sample_rate: u32) -> (u64, Option<(u64, u32)>) {
+    match sample_rate {
+        88200 => (1, None),
+        176_400 => (2, None),
+        192_000 => (3, None),
+        8000 => (4, None),
+        16000 => (5, None),
+        22050 => (6, None),
+        24000 => (7, None),
+        32000 => (8, None),
+        44100 => (9, None),
+        48000 => (10, None),
+        96000 => (11, None),
+        rate if rate % 1000 == 0 && rate / 1000 <= 0xFF => (12, Some((u64::from(rate / 1000), 8))),
+        rate if rate <= 0xFFFF => (13, Some((rate.into(), 16))),
+        rate if rate % 10 == 0 && rate / 10 <= 0xFFFF => (14, Some((u64::from(rate / 10), 16))),
+        // read the sample rate from the STREAMINFO block instead
+        _ => (0, None),
+    }
+}
+
+fn sample_size_code(bits_per_sample: u32) -> u64 {
+    match bits_per_sample {
+        8 => 1,
+        12 => 2,
+        16 => 4,
+        20 => 5,
+        24 => 6,
+        32 => 7,
+        // read the sample size from the STREAMINFO block instead
+        _ => 0,
+    }
+}
+
+#[derive(Clone)]
+struct Subframe<'samples> {
+    samples: &'samples [i64],
+    bps: u32,
+    predictor: Predictor,
+    residual: Option<Residual>,
+    bits: u64,
+}
+
+#[derive(Clone)]
+enum Predictor {
+    Constant,
+    Verbatim,
+    Fixed {
+        order: usize,
+    },
+    Lpc {
+        coeffs: Vec<i64>,
+        precision: u32,
+        shift: u32,
+    },
+}
+
+const MAX_FIXED_ORDER: usize = 4;
+const MAX_LPC_ORDER: usize = 8;
+const LPC_PRECISION: u32 = 12;
+
+impl<'samples> Subframe<'samples> {
+    // Every predictor is tried, and the one producing the smallest subframe is chosen.
+    fn encode(samples: &'samples [i64], bps: u32) -> Self {
+        let len = samples.len() as u64;
+
+        if samples.iter().all(|sample| *sample == samples[0]) {
+            return Self {
+                samples,
+                bps,
+                predictor: Predictor::Constant,
+                residual: None,
+                bits: 8 + u64::from(bps),
+            };
+        }
+
+        let mut best = Self {
+            samples,
+            bps,
+            predictor: Predictor::Verbatim,
+            residual: None,
+            bits: 8 + u64::from(bps) * len,
+        };
+
+        let fixed = (0..=MAX_FIXED_ORDER).map(|order| Predictor::Fixed { order });
+        let lpc = lpc_predictors(samples).into_iter();
+
+        for predictor in fixed.chain(lpc) {
+            let order = predictor.order();
+
+            if order >= samples.len() {
+                continue;
+            }
+
+            let Some(residual) = predictor.residual(samples) else {
+                continue;
+            };
+            let residual = Residual::new(residual, samples.len(), order);
+            let bits = 8 + predictor.header_bits(bps) + residual.bits;
+
+            if bits < best.bits {
+                best = Self {
+                    samples,
+                    bps,
+                    predictor,
+                    residual: Some(residual),
+                    bits,
+                };
+            }
+        }
+
+        best
+    }
+
+    fn write(&self, writer: &mut BitWriter) {
+        let kind = match &self.predictor {
+            Predictor::Constant => 0b00_0000,
+            Predictor::Verbatim => 0b00_0001,
+            Predictor::Fixed { order } => 0b00_1000 | *order as u64,
+            Predictor::Lpc { coeffs, .. } => 0b10_0000 | (coeffs.len() as u64 - 1),
+        };
+
+        writer.write(0, 1); // padding
+        writer.write(kind, 6);
+        writer.write(0, 1); // no wasted bits
+
+        match &self.predictor {
+            Predictor::Constant => writer.write_signed(self.samples[0], self.bps),
+            Predictor::Verbatim => {
+                for sample in self.samples {
+                    writer.write_signed(*sample, self.bps);
+                }
+            }
+            Predictor::Fixed { order } => {
+                for sample in &self.samples[..*order] {
+                    writer.write_signed(*sample, self.bps);
+                }
+            }
+            Predictor::Lpc {
+                coeffs,
+                precision,
+                shift,
+            } => {
+                for sample in &self.samples[..coeffs.len()] {
+                    writer.write_signed(*sample, self.bps);
+                }
+
+                writer.write(u64::from(precision - 1), 4);
+                writer.write(u64::from(*shift), 5);
+
+                for coeff in coeffs {
+                    writer.write_signed(*coeff, *precision);
+                }
+            }
+        }
+
+        if let Some(residual) = &self.residual {
+            residual.write(writer);
+        }
+    }
+}
+
+impl Predictor {
+    fn order(&self) -> usize {
+        match self {
+            Self::Constant | Self::Verbatim => 0,
+            Self::Fixed { order } => *order,
+            Self::Lpc { coeffs, .. } => coeffs.len(),
+        }
+    }
+
+    // number of bits used by warm-up samples and predictor parameters
+    fn header_bits(&self, bps: u32) -> u64 {
+        let warm_up = self.order() as u64 * u64::from(bps);
+
+        match self {
+            Self::Lpc {
+                coeffs, precision, ..
+            } => warm_up + 4 + 5 + coeffs.len() as u64 * u64::from(*precision),
+            _ => warm_up,
+        }
+    }
+
+    // Returns `None` if a residual doesn't fit in a 32-bit integer, which decoders can't handle.
+    fn residual(&self, samples: &[i64]) -> Option<Vec<i64>> {
+        let order = self.order();
+
+        let residual: Vec<_> = match self {
+            Self::Constant | Self::Verbatim => return None,
+            Self::Fixed { order } => {
+                let coeffs: &[i64] = match order {
+                    0 => &[],
+                    1 => &[1],
+                    2 => &[2, -1],
+                    3 => &[3, -3, 1],
+                    _ => &[4, -6, 4, -1],
+                };
+
+                (*order..samples.len())
+                    .map(|i| samples[i] - predict(coeffs, &samples[..i]))
+                    .collect()
+            }
+            Self::Lpc { coeffs, shift, .. } => (order..samples.len())
+                .map(|i| samples[i] - (predict(coeffs, &samples[..i]) >> shift))
+                .collect(),
+        };
+
+        residual.iter().all(|r| i32::try_from(*r).is_ok()).then_some(residual)
+    }
+}
+
+// `coeffs[0]` is applied to the most recent sample
+fn predict(coeffs: &[i64], history: &[i64]) -> i64 {
+    coeffs.iter().zip(history.iter().rev()).map(|(c, s)| c * s).sum()
+}
+
+// Computes quantized linear predictors of every order up to `MAX_LPC_ORDER`.
+#[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+fn lpc_predictors(samples: &[i64]) -> Vec<Predictor> {
+    let max_order = MAX_LPC_ORDER.min(samples.len().saturating_sub(1));
+
+    if max_order == 0 {
+        return Vec::new();
+    }
+
+    // apply a Welch window before computing autocorrelation
+    let len = samples.len() as f64;
+    let windowed: Vec<_> = samples
+        .iter()
+        .enumerate()
+        .map(|(i, sample)| {
+            let x = (2.0 * i as f64 - (len - 1.0)) / (len + 1.0);
+            *sample as f64 * (1.0 - x * x)
+        })
+        .collect();
+
+    let autocorrelation: Vec<f64> = (0..=max_order)
+        .map(|lag| windowed[lag..].iter().zip(&windowed).map(|(a, b)| a * b).sum())
+        .collect();
+
+    if autocorrelation[0] == 0.0 {
+        return Vec::new();
+    }
+
+    // Levinson-Durbin recursion, producing coefficients for each order
+    let mut predictors = Vec::with_capacity(max_order);
+    let mut coeffs = vec![0.0; max_order];
+    let mut error = autocorrelation[0];
+
+    for order in 1..=max_order {
+        let acc = autocorrelation[order]
+            - (1..order)
+                .map(|j| coeffs[j - 1] * autocorrelation[order - j])
+                .sum::<f64>();
+        let reflection = acc / error;
+
+        let previous = coeffs.clone();
+        coeffs[order - 1] = reflection;
+        for j in 1..order {
+            coeffs[j - 1] = previous[j - 1] - reflection * previous[order - j - 1];
+        }
+
+        error *= 1.0 - reflection * reflection;
+
+        if let Some(predictor) = quantize_coeffs(&coeffs[..order]) {
+            predictors.push(predictor);
+        }
+
+        if error <= 0.0 {
+            break;
+        }
+    }
+
+    predictors
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+fn quantize_coeffs(coeffs: &[f64]) -> Option<Predictor> {
+    let max = coeffs.iter().fold(0.0, |max: f64, c| max.max(c.abs()));
+
+    if max == 0.0 || !max.is_finite() {
+        return None;
+    }
+
+    // scale coefficients so the largest one uses all available bits
+    let exponent = max.log2().floor() as i32 + 1;
+    let shift = (LPC_PRECISION as i32 - 1 - exponent).min(15);
+    let shift = u32::try_from(shift).ok()?;
+
+    let limit = f64::from(1 << (LPC_PRECISION - 1));
+    let mut error = 0.0;
+
+    // quantization error is carried over to the next coefficient
+    let quantized = coeffs
+        .iter()
+        .map(|c| {
+            error += c * f64::from(1 << shift);
+            let q = error.round().clamp(-limit, limit - 1.0);
+            error -= q;
+            q as i64
+        })
+        .collect();
+
+    Some(Predictor::Lpc {
+        coeffs: quantized,
+        precision: LPC_PRECISION,
+        shift,
+    })
+}
+
+#[derive(Clone)]
+struct Residual {
+    values: Vec<i64>,
+    block_size: usize,
+    partition_order: u32,
+    params: Vec<u32>,
+    bits: u64,
+}
+
+const MAX_PARTITION_ORDER: u32 = 8;
+
+// Rice parameters are stored in 4 bits, or 5 bits if any parameter exceeds this
+const MAX_RICE_PARAM: u32 = 14;
+const MAX_RICE2_PARAM: u32 = 30;
+
+impl Residual {
+    // The partition order and Rice parameters are chosen to minimize the size of the residual.
+    fn new(values: Vec<i64>, block_size: usize, order: usize) -> Self {
+        let zigzag: Vec<_> = values.iter().map(|r| zigzag(*r)).collect();
+
+        let mut best: Option<Self> = None;
+
+        for partition_order in 0..=MAX_PARTITION_ORDER {
+            let partitions = 1 << partition_order;
+
+            if block_size % partitions != 0 || block_size / partitions <= order {
+                break;
+            }
+
+            let partition_len = block_size / partitions;
+            let mut params = Vec::with_capacity(partitions);
+            let mut bits = 0;
+
+            for index in 0..partitions {
+                let start = (index * partition_len).saturating_sub(order);
+                let end = (index + 1) * partition_len - order;
+                let (param, param_bits) = rice_param(&zigzag[start..end]);
+
+                params.push(param);
+                bits += param_bits;
+            }
+
+            let param_bits = if params.iter().any(|k| *k > MAX_RICE_PARAM) {
+                5
+            } else {
+                4
+            };
+            bits += 2 + 4 + param_bits * partitions as u64;
+
+            if best.as_ref().map_or(true, |best| bits < best.bits) {
+                best = Some(Self {
+                    values: Vec::new(),
+                    block_size,
+                    partition_order,
+                    params,
+                    bits,
+                });
+            }
+        }
+
+        let mut best = best.expect("partition order 0 is always valid");
+        best.values = values;
+        best
+    }
+
+    fn write(&self, writer: &mut BitWriter) {
+        let rice2 = self.params.iter().any(|k| *k > MAX_RICE_PARAM);
+        let param_bits = if rice2 { 5 } else { 4 };
+
+        writer.write(rice2.into(), 2);
+        writer.write(self.partition_order.into(), 4);
+
+        let partition_len = self.block_size / self.params.len();
+        let order = self.block_size - self.values.len();
+
+        for (index, param) in self.params.iter().enumerate() {
+            writer.write((*param).into(), param_bits);
+
+            let start = (index * partition_len).saturating_sub(order);
+            let end = (index + 1) * partition_len - order;
+
+            for value in &self.values[start..end] {
+                let value = zigzag(*value);
+                writer.write_unary(value >> param);
+                writer.write(value & ((1 << param) - 1), *param);
+            }
+        }
+    }
+}
+
+#[allow(clippy::cast_sign_loss)]
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+// Returns the Rice parameter with the smallest estimated size, along with that size in bits.
+fn rice_param(values: &[u64]) -> (u32, u64) {
+    let len = values.len() as u64;
+    let sum: u64 = values.iter().sum();
+
+    (0..=MAX_RICE2_PARAM)
+        .map(|k| (k, len * (u64::from(k) + 1) + (sum >> k)))
+        .min_by_key(|(_, bits)| *bits)
+        .expect("there are multiple Rice parameters to choose from")
+}
+
+// Writes values as a sequence of bits, starting from the most significant bit.
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u64,
+    len: u32,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self {
+            bytes: Vec::new(),
+            acc: 0,
+            len: 0,
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn write(&mut self, value: u64, bits: u32) {
+        debug_assert!(bits <= 32);
+
+        if bits == 0 {
+            return;
+        }
+
+        self.acc = (self.acc << bits) | (value & ((1 << bits) - 1));
+        self.len += bits;
+
+        while self.len >= 8 {
+            self.len -= 8;
+            self.bytes.push((self.acc >> self.len) as u8);
+        }
+    }
+
+    #[allow(clippy::cast_sign_loss)]
+    fn write_signed(&mut self, value: i64, bits: u32) {
+        self.write(value as u64, bits);
+    }
+
+    fn write_unary(&mut self, mut zeros: u64) {
+        while zeros >= 32 {
+            self.write(0, 32);
+            zeros -= 32;
+        }
+
+        #[allow(clippy::cast_possible_truncation)]
+        self.write(1, zeros as u32 + 1);
+    }
+
+    // Writes a number using the variable-length encoding from UTF-8, extended to 31-bit values.
+    fn write_utf8(&mut self, value: u32) {
+        let value = u64::from(value);
+
+        let len = match value {
+            0..=0x7F => {
+                self.write(value, 8);
+                return;
+            }
+            0x80..=0x7FF => 2,
+            0x800..=0xFFFF => 3,
+            0x1_0000..=0x1F_FFFF => 4,
+            0x20_0000..=0x3FF_FFFF => 5,
+            _ => 6,
+        };
+
+        let first_bits = 7 - len;
+        let continuation_bits = 6 * (len - 1);
+
+        // leading byte: `len` ones, a zero, then the most significant bits of the value
+        self.write((1 << len) - 1, len);
+        self.write(0, 1);
+        self.write(value >> continuation_bits, first_bits);
+
+        for index in (0..len - 1).rev() {
+            self.write(0b10, 2);
+            self.write(value >> (6 * index), 6);
+        }
+    }
+
+    // Pads the written bits with zeroes until they fill a whole number of bytes.
+    fn align(&mut self) {
+        if self.len > 0 {
+            self.write(0, 8 - self.len);
+        }
+    }
+
+    // only includes whole bytes
+    fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.align();
+        self.bytes
+    }
+}
+
+fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0, |crc, byte| {
+        (0..8).fold(crc ^ byte, |crc, _| {
+            if crc & 0x80 == 0 {
+                crc << 1
+            } else {
+                (crc << 1) ^ 0x07
+            }
+        })
+    })
+}
+
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0, |crc, byte| {
+        (0..8).fold(crc ^ (u16::from(*byte) << 8), |crc, _| {
+            if crc & 0x8000 == 0 {
+                crc << 1
+            } else {
+                (crc << 1) ^ 0x8005
+            }
+        })
+    })
+}
+
+/// Represents an error that can occur when encoding a stream as FLAC.
+///
+/// See [`FlacErrorKind`] for the different kinds of errors that can occur.
+#[derive(Debug)]
+pub struct FlacError {
+    kind: FlacErrorKind,
+    source: Option<IoError>,
+}
+
+/// A variant of a [`FlacError`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FlacErrorKind {
+    /// The stream had more than 8 channels, which is the maximum supported by FLAC.
+    TooManyChannels,
+    /// The stream's sample rate was too high to be stored in a FLAC file.
+    UnsupportedSampleRate,
+    /// The requested sample format can't be stored in a FLAC file. Only 8, 16, and 24-bit integer samples are supported.
+    UnsupportedSampleFormat,
+    /// Failed to write the file header due to an underlying I/O error.
+    CreateHeader,
+    /// Failed to write an encoded frame of samples to the writer.
+    EncodeFrame,
+    /// Failed to flush the writer after encoding the entire stream.
+    FinishStream,
+}
+
+impl FlacError {
+    pub(super) fn new(kind: FlacErrorKind) -> Self {
+        Self { kind, source: None }
+    }
+
+    fn from_io(kind: FlacErrorKind) -> impl FnOnce(IoError) -> Self {
+        move |source| Self {
+            kind,
+            source: Some(source),
+        }
+    }
+
+    /// Returns the [`FlacErrorKind`] associated with this error.
+    #[must_use]
+    pub fn kind(&self) -> FlacErrorKind {
+        self.kind
+    }
+}
+
+impl Display for FlacError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        self.kind.fmt(f)
+    }
+}
+
+impl Error for FlacError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.source {
+            Some(e) => Some(e),
+            None => None,
+        }
+    }
+}
+
+impl Display for FlacErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(match self {
+            Self::TooManyChannels => "FLAC files can't contain more than 8 channels",
+            Self::UnsupportedSampleRate => "sample rate was too high for a FLAC file",
+            Self::UnsupportedSampleFormat => "sample format is not supported by FLAC",
+            Self::CreateHeader => "failed to encode file header",
+            Self::EncodeFrame => "failed to encode frame of samples",
+            Self::FinishStream => "failed to finalize writing FLAC stream data",
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::encode;
+    use crate::encode::{pcm::quantize, Decode, EncodeError, EncodeOptions};
+    use crate::header::{test::test_info, Loop};
+    use claxon::FlacReader;
+
+    struct Signal {
+        channels: usize,
+        remaining: usize,
+        position: usize,
+    }
+
+    impl Signal {
+        #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+        fn sample(channel: usize, position: usize) -> f32 {
+            // a tone followed by silence, to exercise different predictors
+            if position >= 6000 {
+                return 0.0;
+            }
+
+            let phase = position as f64 * (0.01 + channel as f64 * 0.003);
+            (phase.sin() * 0.8 + (phase * 7.0).cos() * 0.1) as f32
+        }
+    }
+
+    impl Decode for Signal {
+        fn next_block(&mut self) -> Result<Option<Vec<Vec<f32>>>, EncodeError> {
+            let len = self.remaining.min(3000);
+            self.remaining -= len;
+
+            let block = (0..self.channels)
+                .map(|channel| {
+                    (self.position..self.position + len)
+                        .map(|position| Self::sample(channel, position))
+                        .collect()
+                })
+                .collect();
+            self.position += len;
+
+            Ok((len > 0).then_some(block))
+        }
+    }
+
+    #[test]
+    fn encode_lossless() {
+        let stream_loop = Loop::parse(0, 100, 8999).unwrap();
+        let options = EncodeOptions::default().comment("TITLE", "signal");
+
+        for (channels, bits_per_sample) in [(2, 16), (1, 24), (3, 8)] {
+            // the decoder produces fewer samples than expected, so the rest is padded with silence
+            let info = test_info(channels, 10000, 1);
+            let signal = Signal {
+                channels: channels.into(),
+                remaining: 9000,
+                position: 0,
+            };
+
+            let data = encode(
+                bits_per_sample,
+                None,
+                &info,
+                Some(stream_loop),
+                &options,
+                signal,
+                Vec::new(),
+            )
+            .unwrap();
+
+            let mut reader = FlacReader::new(data.as_slice()).unwrap();
+            let stream_info = reader.streaminfo();
+
+            assert_eq!(stream_info.sample_rate, 44100);
+            assert_eq!(stream_info.channels, u32::from(channels));
+            assert_eq!(stream_info.bits_per_sample, u32::from(bits_per_sample));
+            assert_eq!(stream_info.samples, Some(10000));
+
+            let tags: Vec<_> = reader.tags().collect();
+            assert_eq!(
+                tags,
+                [
+                    ("LOOPSTART", "100"),
+                    ("LOOPLENGTH", "8900"),
+                    ("TITLE", "signal")
+                ]
+            );
+
+            let samples: Vec<_> = reader.samples().map(Result::unwrap).collect();
+            let expected: Vec<_> = (0..10000)
+                .flat_map(|position| {
+                    (0..channels.into()).map(move |channel| {
+                        if position < 9000 {
+                            let sample = Signal::sample(channel, position);
+                            quantize(sample.into(), bits_per_sample.into(), 0.0)
+                        } else {
+                            0
+                        }
+                    })
+                })
+                .collect();
+
+            assert_eq!(samples, expected);
+        }
+    }
+}
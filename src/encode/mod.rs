@@ -1,52 +1,218 @@
 //! Various types associated with encoding stream data from sound banks.
 
-use crate::header::{AudioFormat, StreamInfo};
+use crate::header::{AudioFormat, BankFlags, Loop, StreamInfo};
 use crate::read::Reader;
 use std::io::{Read, Write};
 
 mod error;
+mod flac;
+mod options;
 mod pcm;
 mod vorbis;
 mod vorbis_lookup;
 
 pub use error::EncodeError;
-use pcm::{Endianness, Format};
+pub use flac::{FlacError, FlacErrorKind};
+pub use options::{ByteOrder, Container, EncodeOptions, OutputTarget, SampleFormat, VorbisBitrate};
+use pcm::{Format, Layout, Output};
 pub use pcm::{PcmError, PcmErrorKind};
 pub use vorbis::{VorbisError, VorbisErrorKind};
+pub(crate) use vorbis_lookup::VORBIS_LOOKUP;
+
+// Produces blocks of decoded audio samples, with one vector of samples per channel.
+// This allows any decodable stream to be written with any encoder.
+pub(crate) trait Decode {
+    fn next_block(&mut self) -> Result<Option<Vec<Vec<f32>>>, EncodeError>;
+}
 
 pub(crate) fn encode<R: Read, W: Write>(
     format: AudioFormat,
-    flags: u32,
+    flags: BankFlags,
     info: &StreamInfo,
     source: &mut Reader<R>,
     sink: W,
+    options: &EncodeOptions,
 ) -> Result<W, EncodeError> {
+    // loop information is only written if requested
+    let stream_loop = info.stream_loop.filter(|_| options.loop_metadata);
+
     // method of determining sample endianness for PCM24, PCM32, and PCMFLOAT is currently unknown
-    Ok(match format {
-        AudioFormat::Pcm8 => {
-            // endianness doesn't matter when samples are 1 byte wide
-            pcm::encode::<_, _, 1>(Format::Integer, Endianness::Little, info, source, sink)?
-        }
+    let input = match format {
+        // endianness doesn't matter when samples are 1 byte wide
+        AudioFormat::Pcm8 => Layout::new(Format::Integer, 1, ByteOrder::Little),
         AudioFormat::Pcm16 => {
             // determine sample endianness from flags in file header
-            let order = if flags & 0x01 == 1 {
-                Endianness::Big
+            let order = if flags.contains(BankFlags::BIG_ENDIAN_PCM) {
+                ByteOrder::Big
             } else {
-                Endianness::Little
+                ByteOrder::Little
             };
 
-            pcm::encode::<_, _, 2>(Format::Integer, order, info, source, sink)?
+            Layout::new(Format::Integer, 2, order)
         }
-        AudioFormat::Pcm24 => {
-            pcm::encode::<_, _, 3>(Format::Integer, Endianness::Little, info, source, sink)?
-        }
-        AudioFormat::Pcm32 => {
-            pcm::encode::<_, _, 4>(Format::Integer, Endianness::Little, info, source, sink)?
-        }
-        AudioFormat::PcmFloat => {
-            pcm::encode::<_, _, 4>(Format::Float, Endianness::Little, info, source, sink)?
+        AudioFormat::Pcm24 => Layout::new(Format::Integer, 3, ByteOrder::Little),
+        AudioFormat::Pcm32 => Layout::new(Format::Integer, 4, ByteOrder::Little),
+        AudioFormat::PcmFloat => Layout::new(Format::Float, 4, ByteOrder::Little),
+        AudioFormat::Vorbis => {
+            let decoder = vorbis::Decoder::new(info, source)?;
+
+            return match options.container.unwrap_or(Container::Ogg) {
+                Container::Ogg => vorbis::encode(
+                    info.sample_rate,
+                    info.channels,
+                    stream_loop,
+                    options,
+                    decoder,
+                    sink,
+                ),
+                Container::Flac => flac_encode(
+                    Layout::new(Format::Float, 4, ByteOrder::Little),
+                    16,
+                    info,
+                    stream_loop,
+                    options,
+                    decoder,
+                    sink,
+                ),
+                container => pcm::write(
+                    pcm_output(container, Layout::from(SampleFormat::I16), options),
+                    info,
+                    stream_loop,
+                    decoder,
+                    sink,
+                ),
+            };
         }
-        AudioFormat::Vorbis => vorbis::encode(info, source, sink)?,
         _ => return Err(EncodeError::UnsupportedFormat { format }),
-    })
+    };
+
+    match options.container.unwrap_or(Container::Wav) {
+        Container::Ogg => vorbis::encode(
+            info.sample_rate,
+            info.channels,
+            stream_loop,
+            options,
+            pcm::Decoder::new(input, info, source),
+            sink,
+        ),
+        // 32-bit samples are written as 24-bit, since most FLAC decoders don't support wider samples
+        Container::Flac => flac_encode(
+            input,
+            (input.byte_depth.min(3) * 8).try_into().unwrap_or(24),
+            info,
+            stream_loop,
+            options,
+            pcm::Decoder::new(input, info, source),
+            sink,
+        ),
+        container => {
+            let output = pcm_output(container, input, options);
+
+            Ok(match input.byte_depth {
+                1 => pcm::encode::<_, _, 1>(input, output, info, stream_loop, source, sink)?,
+                2 => pcm::encode::<_, _, 2>(input, output, info, stream_loop, source, sink)?,
+                3 => pcm::encode::<_, _, 3>(input, output, info, stream_loop, source, sink)?,
+                _ => pcm::encode::<_, _, 4>(input, output, info, stream_loop, source, sink)?,
+            })
+        }
+    }
+}
+
+// Finds how much of the (possibly truncated) stream data can be decoded: whole PCM frames or complete Vorbis packets.
+// Returns the size of the decodable data in bytes and the number of samples it contains,
+// or `None` if streams of this format can't be decoded.
+pub(crate) fn decodable_prefix(
+    format: AudioFormat,
+    info: &StreamInfo,
+    data: &[u8],
+) -> Option<(usize, u32)> {
+    if format == AudioFormat::Vorbis {
+        return vorbis::decodable_prefix(info, data);
+    }
+
+    let byte_depth = format.pcm_byte_depth()?;
+    let frame_size = usize::from(byte_depth.get()) * usize::from(info.channels.get());
+    let frames = data.len() / frame_size;
+
+    Some((frames * frame_size, u32::try_from(frames).unwrap_or(u32::MAX)))
+}
+
+// Samples are written with the bit depth of the requested format, falling back to the given depth.
+fn flac_encode<D: Decode, W: Write>(
+    input: Layout,
+    fallback_bits: u8,
+    info: &StreamInfo,
+    stream_loop: Option<Loop>,
+    options: &EncodeOptions,
+    decoder: D,
+    sink: W,
+) -> Result<W, EncodeError> {
+    let bits_per_sample = match options.sample_format {
+        None => fallback_bits,
+        Some(SampleFormat::U8) => 8,
+        Some(SampleFormat::I16) => 16,
+        Some(SampleFormat::I24) => 24,
+        Some(_) => return Err(FlacError::new(FlacErrorKind::UnsupportedSampleFormat).into()),
+    };
+
+    let output = Output {
+        layout: Layout::new(Format::Integer, (bits_per_sample / 8).into(), ByteOrder::Little),
+        container: Container::Flac,
+        dither: options.dither,
+    };
+
+    flac::encode(
+        bits_per_sample,
+        output.dither_from(input),
+        info,
+        stream_loop,
+        options,
+        decoder,
+        sink,
+    )
+}
+
+// Samples are written in the requested format, falling back to the given layout.
+fn pcm_output(container: Container, fallback: Layout, options: &EncodeOptions) -> Output {
+    Output {
+        layout: Layout {
+            order: options.byte_order,
+            ..options.sample_format.map_or(fallback, Layout::from)
+        },
+        container,
+        dither: options.dither,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{encode, EncodeOptions};
+    use crate::header::{test::test_info, AudioFormat, BankFlags, Loop, StreamInfo};
+    use crate::read::Reader;
+
+    #[test]
+    fn write_loop_metadata_when_requested() {
+        let data = [0; 8];
+        let info = StreamInfo {
+            stream_loop: Some(Loop::parse(0, 0, 3).unwrap()),
+            ..test_info(1, 4, 8)
+        };
+        let write = |options| {
+            let mut reader = Reader::new(data.as_slice());
+            encode(
+                AudioFormat::Pcm16,
+                BankFlags::from_bits(0),
+                &info,
+                &mut reader,
+                Vec::new(),
+                &options,
+            )
+            .unwrap()
+        };
+        let has_smpl = |wav: &[u8]| wav.windows(4).any(|id| id == b"smpl");
+
+        // loop points are only written when loop metadata is enabled
+        assert!(!has_smpl(&write(EncodeOptions::default())));
+        assert!(has_smpl(&write(EncodeOptions::default().loop_metadata(true))));
+    }
 }
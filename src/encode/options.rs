@@ -0,0 +1,244 @@
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    num::NonZeroU32,
+};
+
+/// Options for encoding stream data, used with [`Stream::write_with`] and [`LazyStream::write_with`].
+///
+/// Options are set by chaining methods on [`EncodeOptions::default`]:
+///
+/// ```
+/// use fsbex::encode::{Container, EncodeOptions, SampleFormat, VorbisBitrate};
+///
+/// let options = EncodeOptions::default()
+///     .container(Container::Wav)
+///     .sample_format(SampleFormat::I16)
+///     .vorbis_bitrate(VorbisBitrate::QualityVbr { target_quality: 0.6 })
+///     .loop_metadata(true)
+///     .comment("ARTIST", "FMOD");
+/// ```
+///
+/// [`Stream::write_with`]: crate::Stream::write_with
+/// [`LazyStream::write_with`]: crate::LazyStream::write_with
+#[derive(Clone, Debug, PartialEq)]
+pub struct EncodeOptions {
+    pub(crate) container: Option<Container>,
+    pub(crate) sample_format: Option<SampleFormat>,
+    pub(crate) byte_order: ByteOrder,
+    pub(crate) dither: bool,
+    pub(crate) vorbis_bitrate: VorbisBitrate,
+    pub(crate) loop_metadata: bool,
+    pub(crate) comments: Vec<(String, String)>,
+}
+
+impl EncodeOptions {
+    /// Sets the container and sample format from a common [`OutputTarget`].
+    ///
+    /// This overrides any container or sample format set previously, and vice versa.
+    #[must_use]
+    pub fn target(mut self, target: OutputTarget) -> Self {
+        let (container, sample_format) = match target {
+            OutputTarget::WavPcm16 => (Container::Wav, Some(SampleFormat::I16)),
+            OutputTarget::WavFloat => (Container::Wav, Some(SampleFormat::F32)),
+            OutputTarget::OggVorbis => (Container::Ogg, None),
+            OutputTarget::Flac => (Container::Flac, None),
+            OutputTarget::RawPcm(format) => (Container::Raw, Some(format)),
+        };
+
+        self.container = Some(container);
+        self.sample_format = sample_format;
+        self
+    }
+
+    /// Sets the container that stream data is written in.
+    ///
+    /// By default, the container depends on the stream's audio format:
+    /// PCM streams are written as WAVE files and Vorbis streams are written as Ogg files.
+    ///
+    /// Streams are transcoded when the container doesn't match the stream's audio format.
+    /// For example, Vorbis streams are decoded when written as WAVE files,
+    /// and PCM streams are encoded with Vorbis when written as Ogg files.
+    #[must_use]
+    pub fn container(mut self, container: Container) -> Self {
+        self.container = Some(container);
+        self
+    }
+
+    /// Sets the format of audio samples written for uncompressed or lossless output.
+    ///
+    /// By default, PCM streams are written in the same format as the stream data,
+    /// and decoded streams (e.g. Vorbis) are written as [`SampleFormat::I16`].
+    /// When a different format is chosen, samples are converted before being written.
+    /// This option has no effect on lossy output, such as Ogg Vorbis.
+    ///
+    /// FLAC output only supports [`SampleFormat::U8`], [`SampleFormat::I16`], and [`SampleFormat::I24`],
+    /// which are written as signed samples of the same width.
+    /// By default, 32-bit PCM streams are written to FLAC as 24-bit samples.
+    #[must_use]
+    pub fn sample_format(mut self, format: SampleFormat) -> Self {
+        self.sample_format = Some(format);
+        self
+    }
+
+    /// Sets the byte order of audio samples written in the [`Container::Raw`] container.
+    ///
+    /// By default, samples are written as little-endian.
+    /// WAVE files always store samples as little-endian, so this option has no effect on them.
+    #[must_use]
+    pub fn byte_order(mut self, order: ByteOrder) -> Self {
+        self.byte_order = order;
+        self
+    }
+
+    /// Sets whether dither is applied when samples are converted to a narrower integer format,
+    /// such as when writing 24-bit or decoded Vorbis streams as 16-bit samples.
+    ///
+    /// Triangular dither is used, which masks quantization distortion with a small amount of noise.
+    /// This is enabled by default.
+    #[must_use]
+    pub fn dither(mut self, enabled: bool) -> Self {
+        self.dither = enabled;
+        self
+    }
+
+    /// Sets the bitrate management strategy for Vorbis output.
+    ///
+    /// By default, [`VorbisBitrate::QualityVbr`] is used with the highest quality setting.
+    #[must_use]
+    pub fn vorbis_bitrate(mut self, bitrate: VorbisBitrate) -> Self {
+        self.vorbis_bitrate = bitrate;
+        self
+    }
+
+    /// Sets whether loop information of a stream is written to the output, if the stream has any.
+    ///
+    /// For WAVE files, loop points are written as `cue ` and `smpl` chunks.
+    /// For Ogg and FLAC files, loop points are written as `LOOPSTART` and `LOOPLENGTH` comments.
+    ///
+    /// This is disabled by default, so that [`Stream::write`](crate::Stream::write) output is unchanged.
+    #[must_use]
+    pub fn loop_metadata(mut self, enabled: bool) -> Self {
+        self.loop_metadata = enabled;
+        self
+    }
+
+    /// Adds a comment tag (e.g. `TITLE`, `ARTIST`) to the output.
+    ///
+    /// Comments are only written to containers that support them, such as Ogg and FLAC.
+    /// Tags must consist of printable ASCII characters other than `=`; otherwise, encoding fails.
+    #[must_use]
+    pub fn comment<T: Into<String>, V: Into<String>>(mut self, tag: T, value: V) -> Self {
+        self.comments.push((tag.into(), value.into()));
+        self
+    }
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        Self {
+            container: None,
+            sample_format: None,
+            byte_order: ByteOrder::Little,
+            dither: true,
+            vorbis_bitrate: VorbisBitrate::QualityVbr {
+                target_quality: 1.0,
+            },
+            loop_metadata: false,
+            comments: Vec::new(),
+        }
+    }
+}
+
+/// Represents common combinations of container and sample format, for use with [`EncodeOptions::target`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum OutputTarget {
+    /// WAVE file with 16-bit integer samples.
+    WavPcm16,
+    /// WAVE file with 32-bit float samples.
+    WavFloat,
+    /// Ogg file containing a Vorbis stream.
+    OggVorbis,
+    /// FLAC file with samples of the same bit depth as the stream, where possible.
+    Flac,
+    /// Headerless PCM with samples of the given format.
+    RawPcm(SampleFormat),
+}
+
+/// Represents file containers that stream data can be written in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Container {
+    /// WAVE file containing uncompressed samples.
+    Wav,
+    /// Ogg file containing a Vorbis stream.
+    Ogg,
+    /// Uncompressed samples with no header, also known as headerless PCM.
+    Raw,
+    /// FLAC file containing losslessly compressed samples.
+    Flac,
+}
+
+impl Display for Container {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(match self {
+            Self::Wav => "WAVE",
+            Self::Ogg => "Ogg",
+            Self::Raw => "raw PCM",
+            Self::Flac => "FLAC",
+        })
+    }
+}
+
+/// Represents formats of uncompressed audio samples.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SampleFormat {
+    /// 8-bit unsigned integer samples.
+    U8,
+    /// 16-bit signed integer samples.
+    I16,
+    /// 24-bit signed integer samples.
+    I24,
+    /// 32-bit signed integer samples.
+    I32,
+    /// 32-bit float (IEEE 754) samples.
+    F32,
+}
+
+/// Represents the order of bytes within audio samples.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ByteOrder {
+    /// Least significant byte first.
+    Little,
+    /// Most significant byte first.
+    Big,
+}
+
+/// Represents strategies for managing the bitrate of Vorbis output.
+///
+/// Bitrates are measured in bits per second.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum VorbisBitrate {
+    /// Variable bitrate targeting a perceptual quality level, from -0.2 (lowest) to 1.0 (highest).
+    QualityVbr {
+        /// The target quality level.
+        target_quality: f32,
+    },
+    /// Variable bitrate targeting an approximate bitrate.
+    Vbr {
+        /// The target bitrate.
+        target_bitrate: NonZeroU32,
+    },
+    /// Average bitrate, allowing short-term variations around the target.
+    Abr {
+        /// The average bitrate.
+        average_bitrate: NonZeroU32,
+    },
+    /// Average bitrate that never exceeds a maximum.
+    ConstrainedAbr {
+        /// The maximum bitrate.
+        maximum_bitrate: NonZeroU32,
+    },
+}
@@ -1,73 +1,255 @@
+use super::options::{ByteOrder, Container, SampleFormat};
+use super::{Decode, EncodeError};
 use crate::{
-    header::StreamInfo,
+    header::{Loop, StreamInfo},
     read::{ReadError, Reader},
 };
 use std::{
     error::Error,
     fmt::{Display, Formatter, Result as FmtResult},
     io::{copy, Error as IoError, Read, Write},
+    iter::zip,
 };
 
 pub(super) fn encode<R: Read, W: Write, const BYTE_DEPTH: usize>(
-    format: Format,
-    order: Endianness,
+    input: Layout,
+    output: Output,
     info: &StreamInfo,
+    stream_loop: Option<Loop>,
     source: &mut Reader<R>,
     mut sink: W,
 ) -> Result<W, PcmError> {
-    // write the WAVE file header
-    write_header(
-        info.size.get(),
-        info.channels.get().into(),
-        info.sample_rate.get(),
-        format,
-        BYTE_DEPTH.try_into().expect("byte depth is less than u16::MAX"),
-        &mut sink,
-    )
-    .map_err(PcmError::from_io(PcmErrorKind::CreateHeader))?;
+    debug_assert_eq!(usize::from(input.byte_depth), BYTE_DEPTH);
+
+    let output = output.normalized();
+
+    if output.container == Container::Wav {
+        // The size of the output data differs from the stream size when samples are converted.
+        let data_size = u64::from(info.size.get()) / u64::from(input.byte_depth)
+            * u64::from(output.layout.byte_depth);
+
+        // write the WAVE file header
+        write_header(
+            data_size,
+            info.channels.get().into(),
+            info.sample_rate.get(),
+            output.layout,
+            stream_loop,
+            &mut sink,
+        )?;
+    }
 
     let start_pos = source.position();
-    let stream_size = info.size.get() as usize;
+    let stream_size = u64::from(info.size.get());
+    let output_layout = output.layout;
 
-    // Stream samples are encoded as little-endian.
-    // However, samples can be stored as big-endian; when this happens, the samples have to be converted.
-    // Otherwise, the stream data can be directly copied from reader to writer.
+    // If the stream samples are already stored in the output layout,
+    // the stream data can be directly copied from reader to writer.
+    // Otherwise, samples are either reversed (when only their byte order differs) or fully converted.
 
-    if format == Format::Float || order == Endianness::Little {
+    if input.is_compatible(output_layout) {
         // There could be more data after the stream, so a limit is placed on the number of bytes read.
         return copy(&mut source.limit(stream_size), &mut sink)
             .map(|_| sink)
             .map_err(PcmError::from_io(PcmErrorKind::EncodeStream));
     }
 
+    let reverse_only =
+        input.format == output_layout.format && input.byte_depth == output_layout.byte_depth;
+    let mut dither = output.dither_from(input);
+    let mut buf = [0; 4];
+
     while source.position() - start_pos < stream_size {
         let mut sample = source
             .take_const::<BYTE_DEPTH>()
             .map_err(PcmError::from_read(PcmErrorKind::DecodeSample))?;
 
-        // This is optimized out when BYTE_DEPTH == 1
-        sample.reverse();
+        let encoded = if reverse_only {
+            // This is optimized out when BYTE_DEPTH == 1
+            sample.reverse();
+            &sample[..]
+        } else {
+            let noise = dither.as_mut().map_or(0.0, Dither::next);
+            output_layout.encode_sample(input.decode_sample(&sample), noise, &mut buf)
+        };
+
+        sink.write_all(encoded)
+            .map_err(PcmError::from_io(PcmErrorKind::EncodeSample))?;
+    }
+
+    sink.flush()
+        .map(|()| sink)
+        .map_err(PcmError::from_io(PcmErrorKind::FinishStream))
+}
+
+// Writes blocks of decoded samples, such as those from a Vorbis stream.
+// Exactly as many samples as the stream header specifies are written: extra samples are discarded,
+// and silence is added if the decoder runs out of samples early.
+pub(super) fn write<D: Decode, W: Write>(
+    output: Output,
+    info: &StreamInfo,
+    stream_loop: Option<Loop>,
+    mut decoder: D,
+    mut sink: W,
+) -> Result<W, EncodeError> {
+    let output = output.normalized();
+    let channels = usize::from(info.channels.get());
+    let num_samples = info.num_samples.get() as usize;
+
+    if output.container == Container::Wav {
+        let data_size = u64::from(info.num_samples.get())
+            * channels as u64
+            * u64::from(output.layout.byte_depth);
+
+        write_header(
+            data_size,
+            info.channels.get().into(),
+            info.sample_rate.get(),
+            output.layout,
+            stream_loop,
+            &mut sink,
+        )?;
+    }
+
+    let mut dither = output.dither_from(Layout::new(Format::Float, 4, ByteOrder::Little));
+    let mut buf = [0; 4];
+    let mut written = 0;
 
-        sink.write_all(&sample)
+    while written < num_samples {
+        let Some(block) = decoder.next_block()? else {
+            break;
+        };
+
+        let block_len = block.first().map_or(0, Vec::len).min(num_samples - written);
+
+        for index in 0..block_len {
+            for channel in &block {
+                let noise = dither.as_mut().map_or(0.0, Dither::next);
+                let encoded = output.layout.encode_sample(channel[index].into(), noise, &mut buf);
+
+                sink.write_all(encoded)
+                    .map_err(PcmError::from_io(PcmErrorKind::EncodeSample))?;
+            }
+        }
+
+        written += block_len;
+    }
+
+    // pad the output with silence if the stream ended early
+    for _ in written * channels..num_samples * channels {
+        let encoded = output.layout.encode_sample(0.0, 0.0, &mut buf);
+
+        sink.write_all(encoded)
             .map_err(PcmError::from_io(PcmErrorKind::EncodeSample))?;
     }
 
     sink.flush()
         .map(|()| sink)
         .map_err(PcmError::from_io(PcmErrorKind::FinishStream))
+        .map_err(Into::into)
+}
+
+// number of samples per channel in each block produced by `Decoder`
+const BLOCK_LEN: usize = 4096;
+
+// Decodes PCM stream data into blocks of samples, for encoders that don't operate on raw bytes.
+pub(super) struct Decoder<'reader, R: Read> {
+    input: Layout,
+    channels: usize,
+    source: &'reader mut Reader<R>,
+    start_pos: u64,
+    stream_size: u64,
+}
+
+impl<'reader, R: Read> Decoder<'reader, R> {
+    pub(super) fn new(input: Layout, info: &StreamInfo, source: &'reader mut Reader<R>) -> Self {
+        Self {
+            input,
+            channels: info.channels.get().into(),
+            start_pos: source.position(),
+            stream_size: info.size.get().into(),
+            source,
+        }
+    }
+}
+
+impl<R: Read> Decode for Decoder<'_, R> {
+    #[allow(clippy::cast_possible_truncation)]
+    fn next_block(&mut self) -> Result<Option<Vec<Vec<f32>>>, EncodeError> {
+        let byte_depth = usize::from(self.input.byte_depth);
+        let frame_size = byte_depth * self.channels;
+        let remaining = self.stream_size - (self.source.position() - self.start_pos);
+        let block_len = (remaining / frame_size as u64).min(BLOCK_LEN as u64) as usize;
+
+        // incomplete frames at the end of the stream are ignored
+        if block_len == 0 {
+            return Ok(None);
+        }
+
+        let data = self
+            .source
+            .take(block_len * frame_size)
+            .map_err(PcmError::from_read(PcmErrorKind::DecodeSample))?;
+
+        let mut block = vec![Vec::with_capacity(block_len); self.channels];
+
+        for frame in data.chunks_exact(frame_size) {
+            for (channel, sample) in zip(&mut block, frame.chunks_exact(byte_depth)) {
+                channel.push(self.input.decode_sample(sample) as f32);
+            }
+        }
+
+        Ok(Some(block))
+    }
 }
 
+// Sizes in the header are 32-bit, so this fails if the whole file would be larger than 4 GiB.
 fn write_header<W: Write>(
-    file_size: u32,
+    data_size: u64,
     channels: u16,
     sample_rate: u32,
-    format: Format,
-    byte_depth: u16,
+    layout: Layout,
+    stream_loop: Option<Loop>,
+    sink: &mut W,
+) -> Result<(), PcmError> {
+    // "WAVE" + "fmt " chunk + "data" chunk header, along with the loop chunks if they are written
+    let header_size = 4
+        + (8 + FMT_CHUNK_SIZE)
+        + stream_loop.map_or(0, |_| (8 + CUE_CHUNK_SIZE) + (8 + SMPL_CHUNK_SIZE))
+        + 8;
+
+    let riff_size = u32::try_from(u64::from(header_size) + data_size)
+        .map_err(|_| PcmError::new(PcmErrorKind::OutputTooLarge))?;
+
+    write_chunks(
+        riff_size,
+        riff_size - header_size,
+        channels,
+        sample_rate,
+        layout,
+        stream_loop,
+        sink,
+    )
+    .map_err(PcmError::from_io(PcmErrorKind::CreateHeader))
+}
+
+fn write_chunks<W: Write>(
+    riff_size: u32,
+    data_size: u32,
+    channels: u16,
+    sample_rate: u32,
+    layout: Layout,
+    stream_loop: Option<Loop>,
     sink: &mut W,
 ) -> Result<(), IoError> {
     // WAVE file header information taken from:
     // [1]: https://www-mmsp.ece.mcgill.ca/Documents/AudioFormats/WAVE/WAVE.html
     // [2]: http://soundfile.sapp.org/doc/WaveFormat/
+    // [3]: https://www.recordingblogs.com/wiki/sample-chunk-of-a-wave-file
+
+    let Layout {
+        format, byte_depth, ..
+    } = layout;
 
     let format_id = match format {
         Format::Integer => 1u16,
@@ -76,18 +258,71 @@ fn write_header<W: Write>(
     let bytes_per_second = sample_rate * u32::from(channels) * u32::from(byte_depth);
 
     sink.write_all(b"RIFF")?;
-    sink.write_all(&(file_size - 8).to_le_bytes())?;
+    sink.write_all(&riff_size.to_le_bytes())?;
     sink.write_all(b"WAVE")?;
     sink.write_all(b"fmt ")?;
-    sink.write_all(&16u32.to_le_bytes())?;
+    sink.write_all(&FMT_CHUNK_SIZE.to_le_bytes())?;
     sink.write_all(&format_id.to_le_bytes())?;
     sink.write_all(&channels.to_le_bytes())?;
     sink.write_all(&sample_rate.to_le_bytes())?;
     sink.write_all(&bytes_per_second.to_le_bytes())?;
     sink.write_all(&(channels * byte_depth).to_le_bytes())?;
     sink.write_all(&(byte_depth * 8).to_le_bytes())?;
+
+    if let Some(stream_loop) = stream_loop {
+        write_loop_chunks(stream_loop, sample_rate, sink)?;
+    }
+
+    sink.write_all(b"data")?;
+    sink.write_all(&data_size.to_le_bytes())?;
+
+    Ok(())
+}
+
+const FMT_CHUNK_SIZE: u32 = 16;
+const CUE_CHUNK_SIZE: u32 = 4 + 24;
+const SMPL_CHUNK_SIZE: u32 = 36 + 24;
+
+// identifier shared by the cue point and the sample loop referring to it
+const LOOP_CUE_POINT_ID: u32 = 1;
+
+fn write_loop_chunks<W: Write>(
+    stream_loop: Loop,
+    sample_rate: u32,
+    sink: &mut W,
+) -> Result<(), IoError> {
+    // A single cue point marks the start of the loop.
+    // Positions in both chunks are measured in sample frames.
+    sink.write_all(b"cue ")?;
+    sink.write_all(&CUE_CHUNK_SIZE.to_le_bytes())?;
+    sink.write_all(&1u32.to_le_bytes())?; // number of cue points
+    sink.write_all(&LOOP_CUE_POINT_ID.to_le_bytes())?;
+    sink.write_all(&stream_loop.start().to_le_bytes())?; // play order position
     sink.write_all(b"data")?;
-    sink.write_all(&(file_size - 40).to_le_bytes())?;
+    sink.write_all(&0u32.to_le_bytes())?; // chunk start
+    sink.write_all(&0u32.to_le_bytes())?; // block start
+    sink.write_all(&stream_loop.start().to_le_bytes())?; // sample offset
+
+    // The sampler chunk describes a single forward loop that repeats indefinitely.
+    let sample_period = 1_000_000_000 / sample_rate;
+
+    sink.write_all(b"smpl")?;
+    sink.write_all(&SMPL_CHUNK_SIZE.to_le_bytes())?;
+    sink.write_all(&0u32.to_le_bytes())?; // manufacturer
+    sink.write_all(&0u32.to_le_bytes())?; // product
+    sink.write_all(&sample_period.to_le_bytes())?; // sample period (nanoseconds)
+    sink.write_all(&60u32.to_le_bytes())?; // MIDI unity note (middle C)
+    sink.write_all(&0u32.to_le_bytes())?; // MIDI pitch fraction
+    sink.write_all(&0u32.to_le_bytes())?; // SMPTE format
+    sink.write_all(&0u32.to_le_bytes())?; // SMPTE offset
+    sink.write_all(&1u32.to_le_bytes())?; // number of sample loops
+    sink.write_all(&0u32.to_le_bytes())?; // sampler data size
+    sink.write_all(&LOOP_CUE_POINT_ID.to_le_bytes())?;
+    sink.write_all(&0u32.to_le_bytes())?; // loop type (forward)
+    sink.write_all(&stream_loop.start().to_le_bytes())?;
+    sink.write_all(&(stream_loop.end().get() - 1).to_le_bytes())?; // last sample (inclusive)
+    sink.write_all(&0u32.to_le_bytes())?; // fraction
+    sink.write_all(&0u32.to_le_bytes())?; // play count (infinite)
 
     Ok(())
 }
@@ -98,10 +333,148 @@ pub(super) enum Format {
     Float,
 }
 
+// describes how samples are stored in stream data or in the output
 #[derive(Clone, Copy, PartialEq, Eq)]
-pub(super) enum Endianness {
-    Little,
-    Big,
+pub(super) struct Layout {
+    pub(super) format: Format,
+    pub(super) byte_depth: u16,
+    pub(super) order: ByteOrder,
+}
+
+impl Layout {
+    pub(super) fn new(format: Format, byte_depth: u16, order: ByteOrder) -> Self {
+        Self {
+            format,
+            byte_depth,
+            order,
+        }
+    }
+
+    // byte order is irrelevant when samples are 1 byte wide
+    fn is_compatible(self, other: Self) -> bool {
+        self.format == other.format
+            && self.byte_depth == other.byte_depth
+            && (self.order == other.order || self.byte_depth == 1)
+    }
+
+    // Samples are converted to values in the range [-1.0, 1.0).
+    // f64 can exactly represent every integer sample, so widening conversions are lossless.
+    fn decode_sample(self, sample: &[u8]) -> f64 {
+        let mut bytes = [0; 4];
+        bytes[..sample.len()].copy_from_slice(sample);
+
+        if self.order == ByteOrder::Big {
+            bytes[..sample.len()].reverse();
+        }
+
+        match (self.format, self.byte_depth) {
+            (Format::Float, _) => f32::from_le_bytes(bytes).into(),
+            // 8-bit samples are unsigned
+            (Format::Integer, 1) => (f64::from(bytes[0]) - 128.0) / 128.0,
+            (Format::Integer, depth) => {
+                // shift the sample into the most significant bytes so its sign is preserved
+                let shift = 32 - u32::from(depth) * 8;
+                f64::from(i32::from_le_bytes(bytes) << shift) / 2f64.powi(31)
+            }
+        }
+    }
+
+    // `noise` is added to integer samples before rounding; see `quantize()`
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn encode_sample(self, value: f64, noise: f64, buf: &mut [u8; 4]) -> &[u8] {
+        let depth = usize::from(self.byte_depth);
+
+        *buf = match (self.format, self.byte_depth) {
+            (Format::Float, _) => (value as f32).to_le_bytes(),
+            // 8-bit samples are unsigned
+            (Format::Integer, 1) => [(quantize(value, 8, noise) + 128) as u8, 0, 0, 0],
+            (Format::Integer, depth) => quantize(value, u32::from(depth) * 8, noise).to_le_bytes(),
+        };
+
+        if self.order == ByteOrder::Big {
+            buf[..depth].reverse();
+        }
+
+        &buf[..depth]
+    }
+}
+
+// Converts a sample in the range [-1.0, 1.0) to a signed integer with the given bit depth, clipping if needed.
+// `noise` is added before rounding, and is measured in units of the least significant bit.
+#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+pub(super) fn quantize(value: f64, bits: u32, noise: f64) -> i32 {
+    let scale = 2f64.powi(bits as i32 - 1);
+    (value * scale + noise).round().clamp(-scale, scale - 1.0) as i32
+}
+
+impl From<SampleFormat> for Layout {
+    fn from(value: SampleFormat) -> Self {
+        let (format, byte_depth) = match value {
+            SampleFormat::U8 => (Format::Integer, 1),
+            SampleFormat::I16 => (Format::Integer, 2),
+            SampleFormat::I24 => (Format::Integer, 3),
+            SampleFormat::I32 => (Format::Integer, 4),
+            SampleFormat::F32 => (Format::Float, 4),
+        };
+
+        Self::new(format, byte_depth, ByteOrder::Little)
+    }
+}
+
+// describes the container and sample layout that samples are written in
+#[derive(Clone, Copy)]
+pub(super) struct Output {
+    pub(super) layout: Layout,
+    pub(super) container: Container,
+    pub(super) dither: bool,
+}
+
+impl Output {
+    // WAVE files always store samples as little-endian
+    fn normalized(self) -> Self {
+        match self.container {
+            Container::Wav => Self {
+                layout: Layout {
+                    order: ByteOrder::Little,
+                    ..self.layout
+                },
+                ..self
+            },
+            _ => self,
+        }
+    }
+
+    // Dither is only needed when samples lose precision by being converted to a narrower integer format.
+    pub(super) fn dither_from(self, input: Layout) -> Option<Dither> {
+        let narrowing = self.layout.format == Format::Integer
+            && (input.format == Format::Float || input.byte_depth > self.layout.byte_depth);
+
+        (self.dither && narrowing).then(Dither::new)
+    }
+}
+
+// Generates triangular (TPDF) dither noise with an amplitude of 1 LSB.
+// A fixed seed is used so that output is reproducible.
+pub(super) struct Dither {
+    state: u32,
+}
+
+impl Dither {
+    fn new() -> Self {
+        Self { state: 0x2545_F491 }
+    }
+
+    // xorshift32 PRNG, producing a value in the range [0.0, 1.0)
+    fn random(&mut self) -> f64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        f64::from(self.state) / 2f64.powi(32)
+    }
+
+    pub(super) fn next(&mut self) -> f64 {
+        self.random() - self.random()
+    }
 }
 
 /// Represents an error that can occur when encoding a PCM stream.
@@ -110,7 +483,7 @@ pub(super) enum Endianness {
 #[derive(Debug)]
 pub struct PcmError {
     kind: PcmErrorKind,
-    source: PcmErrorSource,
+    source: Option<PcmErrorSource>,
 }
 
 /// A variant of a [`PcmError`].
@@ -127,6 +500,8 @@ pub enum PcmErrorKind {
     EncodeSample,
     /// Failed to flush the writer after encoding the entire stream.
     FinishStream,
+    /// The converted stream data was too large to fit in a WAVE file.
+    OutputTooLarge,
 }
 
 #[derive(Debug)]
@@ -136,17 +511,21 @@ enum PcmErrorSource {
 }
 
 impl PcmError {
+    fn new(kind: PcmErrorKind) -> Self {
+        Self { kind, source: None }
+    }
+
     fn from_io(kind: PcmErrorKind) -> impl FnOnce(IoError) -> Self {
         move |source| Self {
             kind,
-            source: PcmErrorSource::Io(source),
+            source: Some(PcmErrorSource::Io(source)),
         }
     }
 
     fn from_read(kind: PcmErrorKind) -> impl FnOnce(ReadError) -> Self {
         move |source| Self {
             kind,
-            source: PcmErrorSource::Read(source),
+            source: Some(PcmErrorSource::Read(source)),
         }
     }
 
@@ -166,8 +545,11 @@ impl Display for PcmError {
 impl Error for PcmError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match &self.source {
-            PcmErrorSource::Io(e) => Some(e),
-            PcmErrorSource::Read(e) => Some(e),
+            Some(source) => match source {
+                PcmErrorSource::Io(e) => Some(e),
+                PcmErrorSource::Read(e) => Some(e),
+            },
+            None => None,
         }
     }
 }
@@ -180,6 +562,255 @@ impl Display for PcmErrorKind {
             Self::DecodeSample => "failed to decode sample from PCM stream",
             Self::EncodeSample => "failed to encode sample",
             Self::FinishStream => "failed to finalize writing PCM stream data",
+            Self::OutputTooLarge => "converted PCM stream data was too large for a WAVE file",
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{encode, write, write_header, Decoder, Format, Layout, Output, PcmErrorKind};
+    use crate::encode::options::{ByteOrder, Container};
+    use crate::encode::{Decode, EncodeError};
+    use crate::header::{test::test_info, Loop};
+    use crate::read::Reader;
+
+    #[test]
+    fn header_without_loop() {
+        let mut buf = Vec::new();
+        write_header(1000, 2, 44100, pcm16(), None, &mut buf).unwrap();
+
+        assert_eq!(buf.len(), 44);
+        assert_eq!(&buf[0..4], b"RIFF");
+        assert_eq!(buf[4..8], (36u32 + 1000).to_le_bytes());
+        assert_eq!(&buf[36..40], b"data");
+        assert_eq!(buf[40..44], 1000u32.to_le_bytes());
+    }
+
+    #[test]
+    fn header_too_large() {
+        let stream_loop = Loop::parse(0, 100, 900).unwrap();
+        let max_data_size = u64::from(u32::MAX) - 36;
+
+        let mut buf = Vec::new();
+        write_header(max_data_size, 2, 44100, pcm16(), None, &mut buf).unwrap();
+        assert_eq!(buf[4..8], u32::MAX.to_le_bytes());
+
+        // the loop chunks don't fit along with the data anymore
+        let err =
+            write_header(max_data_size, 2, 44100, pcm16(), Some(stream_loop), &mut Vec::new())
+                .unwrap_err();
+        assert_eq!(err.kind(), PcmErrorKind::OutputTooLarge);
+
+        let err = write_header(u64::from(u32::MAX), 2, 44100, pcm16(), None, &mut Vec::new())
+            .unwrap_err();
+        assert_eq!(err.kind(), PcmErrorKind::OutputTooLarge);
+    }
+
+    fn pcm16() -> Layout {
+        Layout::new(Format::Integer, 2, ByteOrder::Little)
+    }
+
+    #[test]
+    fn header_with_loop() {
+        let stream_loop = Loop::parse(0, 100, 900).unwrap();
+
+        let mut buf = Vec::new();
+        write_header(1000, 2, 44100, pcm16(), Some(stream_loop), &mut buf).unwrap();
+
+        assert_eq!(buf.len(), 44 + 36 + 68);
+        assert_eq!(buf[4..8], (36u32 + 36 + 68 + 1000).to_le_bytes());
+
+        // cue chunk
+        assert_eq!(&buf[36..40], b"cue ");
+        assert_eq!(buf[40..44], 28u32.to_le_bytes());
+        assert_eq!(buf[68..72], 100u32.to_le_bytes());
+
+        // sampler chunk
+        assert_eq!(&buf[72..76], b"smpl");
+        assert_eq!(buf[76..80], 60u32.to_le_bytes());
+        assert_eq!(buf[124..128], 100u32.to_le_bytes());
+        assert_eq!(buf[128..132], 900u32.to_le_bytes());
+
+        // data chunk
+        assert_eq!(&buf[140..144], b"data");
+        assert_eq!(buf[144..148], 1000u32.to_le_bytes());
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp, clippy::similar_names)]
+    fn convert_samples() {
+        let i16_le = Layout::new(Format::Integer, 2, ByteOrder::Little);
+        let i16_be = Layout::new(Format::Integer, 2, ByteOrder::Big);
+        let i24 = Layout::new(Format::Integer, 3, ByteOrder::Little);
+        let u8 = Layout::new(Format::Integer, 1, ByteOrder::Little);
+        let f32 = Layout::new(Format::Float, 4, ByteOrder::Little);
+        let mut buf = [0; 4];
+
+        assert_eq!(i16_le.decode_sample(&[0x00, 0x80]), -1.0);
+        assert_eq!(i16_be.decode_sample(&[0x40, 0x00]), 0.5);
+        assert_eq!(u8.decode_sample(&[0x80]), 0.0);
+
+        // widening is lossless
+        let value = i16_le.decode_sample(&[0x34, 0x12]);
+        assert_eq!(i24.encode_sample(value, 0.0, &mut buf), [0x00, 0x34, 0x12]);
+
+        // narrowing rounds to the nearest sample
+        let value = i24.decode_sample(&[0x80, 0x34, 0x12]);
+        assert_eq!(i16_le.encode_sample(value, 0.0, &mut buf), [0x35, 0x12]);
+
+        // out-of-range float samples are clipped
+        assert_eq!(i16_be.encode_sample(1.5, 0.0, &mut buf), [0x7F, 0xFF]);
+        assert_eq!(f32.encode_sample(-1.0, 0.0, &mut buf), (-1.0f32).to_le_bytes());
+        assert_eq!(u8.encode_sample(-1.0, 0.0, &mut buf), [0x00]);
+    }
+
+    fn output(layout: Layout, container: Container) -> Output {
+        Output {
+            layout,
+            container,
+            dither: false,
+        }
+    }
+
+    #[test]
+    fn encode_raw_samples() {
+        let data = [0x01, 0x00, 0x02, 0x00, 0x03, 0x00, 0x04, 0x00];
+        let info = test_info(2, 2, 8);
+        let input = Layout::new(Format::Integer, 2, ByteOrder::Big);
+
+        let mut reader = Reader::new(data.as_slice());
+        let layout = Layout::new(Format::Integer, 2, ByteOrder::Little);
+        let sink = encode::<_, _, 2>(
+            input,
+            output(layout, Container::Raw),
+            &info,
+            None,
+            &mut reader,
+            Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(sink, [0x00, 0x01, 0x00, 0x02, 0x00, 0x03, 0x00, 0x04]);
+
+        let mut reader = Reader::new(data.as_slice());
+        let layout = Layout::new(Format::Integer, 1, ByteOrder::Little);
+        let sink = encode::<_, _, 2>(
+            input,
+            output(layout, Container::Raw),
+            &info,
+            None,
+            &mut reader,
+            Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(sink, [0x81, 0x82, 0x83, 0x84]);
+
+        let mut reader = Reader::new(data.as_slice());
+        let sink = encode::<_, _, 2>(
+            input,
+            output(layout, Container::Wav),
+            &info,
+            None,
+            &mut reader,
+            Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(sink.len(), 44 + 4);
+        assert_eq!(sink[40..44], 4u32.to_le_bytes());
+    }
+
+    #[test]
+    fn decode_blocks() {
+        let data = [0x00, 0x40, 0x00, 0xC0, 0x00, 0x00, 0x00, 0x00, 0xFF];
+        let info = test_info(2, 2, 9);
+        let input = Layout::new(Format::Integer, 2, ByteOrder::Little);
+
+        let mut reader = Reader::new(data.as_slice());
+        let mut decoder = Decoder::new(input, &info, &mut reader);
+
+        // incomplete frames at the end of the stream are ignored
+        assert_eq!(
+            decoder.next_block().unwrap(),
+            Some(vec![vec![0.5, 0.0], vec![-0.5, 0.0]])
+        );
+        assert_eq!(decoder.next_block().unwrap(), None);
+    }
+
+    struct Blocks(Vec<Vec<Vec<f32>>>);
+
+    impl Decode for Blocks {
+        fn next_block(&mut self) -> Result<Option<Vec<Vec<f32>>>, EncodeError> {
+            Ok((!self.0.is_empty()).then(|| self.0.remove(0)))
+        }
+    }
+
+    #[test]
+    fn write_decoded_blocks() {
+        let layout = Layout::new(Format::Integer, 1, ByteOrder::Little);
+
+        // extra samples are discarded
+        let blocks = Blocks(vec![vec![vec![0.5, 0.5], vec![-0.5, -0.5]]]);
+        let sink = write(
+            output(layout, Container::Raw),
+            &test_info(2, 1, 2),
+            None,
+            blocks,
+            Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(sink, [0xC0, 0x40]);
+
+        // missing samples are replaced with silence
+        let blocks = Blocks(vec![vec![vec![0.5], vec![-0.5]]]);
+        let sink = write(
+            output(layout, Container::Raw),
+            &test_info(2, 2, 4),
+            None,
+            blocks,
+            Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(sink, [0xC0, 0x40, 0x80, 0x80]);
+
+        let blocks = Blocks(Vec::new());
+        let sink = write(
+            output(layout, Container::Wav),
+            &test_info(2, 3, 6),
+            None,
+            blocks,
+            Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(sink[40..44], 6u32.to_le_bytes());
+        assert_eq!(sink[44..], [0x80; 6]);
+    }
+
+    #[test]
+    fn dither_is_bounded() {
+        let i16_le = Layout::new(Format::Integer, 2, ByteOrder::Little);
+        let f32 = Layout::new(Format::Float, 4, ByteOrder::Little);
+        let i24 = Layout::new(Format::Integer, 3, ByteOrder::Little);
+
+        let dithered = Output {
+            layout: i16_le,
+            container: Container::Raw,
+            dither: true,
+        };
+        assert!(dithered.dither_from(f32).is_some());
+        assert!(dithered.dither_from(i24).is_some());
+        assert!(dithered.dither_from(i16_le).is_none());
+        assert!(output(i16_le, Container::Raw).dither_from(f32).is_none());
+
+        let mut dither = dithered.dither_from(f32).unwrap();
+        let mut buf = [0; 4];
+
+        for _ in 0..1000 {
+            let noise = dither.next();
+            assert!(noise > -1.0 && noise < 1.0);
+
+            let sample =
+                i16::from_le_bytes(i16_le.encode_sample(0.0, noise, &mut buf).try_into().unwrap());
+            assert!((-1..=1).contains(&sample));
+        }
+    }
+}
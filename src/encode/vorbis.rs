@@ -1,5 +1,7 @@
+use super::options::{EncodeOptions, VorbisBitrate};
 use super::vorbis_lookup::VORBIS_LOOKUP;
-use crate::header::StreamInfo;
+use super::{Decode, EncodeError};
+use crate::header::{Loop, StreamInfo};
 use crate::read::{ReadError, Reader};
 use lewton::{
     audio::{read_audio_packet_generic, PreviousWindowRight},
@@ -9,65 +11,155 @@ use std::{
     error::Error,
     fmt::{Display, Formatter, Result as FmtResult},
     io::{Error as IoError, Read, Write},
+    num::{NonZeroU32, NonZeroU8},
 };
 use tap::Pipe;
 use vorbis_rs::{VorbisBitrateManagementStrategy, VorbisEncoderBuilder};
 
-pub(super) fn encode<R: Read, W: Write>(
-    info: &StreamInfo,
-    source: &mut Reader<R>,
+pub(super) fn encode<D: Decode, W: Write>(
+    sample_rate: NonZeroU32,
+    channels: NonZeroU8,
+    stream_loop: Option<Loop>,
+    options: &EncodeOptions,
+    mut decoder: D,
     sink: W,
-) -> Result<W, VorbisError> {
-    // The stream should have contained the CRC32 of a setup header in a header chunk.
-    // Otherwise, the stream cannot be encoded correctly.
-    let crc32 = info
-        .vorbis_crc32
-        .ok_or_else(|| VorbisError::new(VorbisErrorKind::MissingCrc32))?;
-
-    // construct headers needed for decoding packets from stream data
-    let (id_header, setup_header) =
-        init_headers(info.sample_rate.get(), info.channels.get(), crc32)?;
-
-    // construct encoder that prioritizes audio quality
-    let mut encoder = VorbisEncoderBuilder::new(info.sample_rate, info.channels, sink)
+) -> Result<W, EncodeError> {
+    // Loop points are stored as comments, following the convention used by game engines and audio players.
+    // Both values are measured in samples.
+    let loop_comments = stream_loop.map(|stream_loop| {
+        [
+            ("LOOPSTART", stream_loop.start().to_string()),
+            ("LOOPLENGTH", stream_loop.len().to_string()),
+        ]
+    });
+
+    // construct encoder with the requested bitrate and comments
+    let mut encoder = VorbisEncoderBuilder::new(sample_rate, channels, sink)
+        .map_err(VorbisError::from_vorbis(VorbisErrorKind::CreateEncoder))?
+        .bitrate_management_strategy(options.vorbis_bitrate.into())
+        .comment_tags(loop_comments.into_iter().flatten())
+        .map_err(VorbisError::from_vorbis(VorbisErrorKind::CreateEncoder))?
+        .comment_tags(
+            options
+                .comments
+                .iter()
+                .map(|(tag, value)| (tag.as_str(), value.as_str())),
+        )
         .map_err(VorbisError::from_vorbis(VorbisErrorKind::CreateEncoder))?
-        .bitrate_management_strategy(VorbisBitrateManagementStrategy::QualityVbr {
-            target_quality: 1.0,
-        })
         .build()
         .map_err(VorbisError::from_vorbis(VorbisErrorKind::CreateEncoder))?;
 
-    let start_pos = source.position();
-    let stream_size = info.size.get() as usize;
-    let mut window = PreviousWindowRight::new();
+    while let Some(block) = decoder.next_block()? {
+        encoder
+            .encode_audio_block(block)
+            .map_err(VorbisError::from_vorbis(VorbisErrorKind::EncodeBlock))?;
+    }
+
+    encoder
+        .finish()
+        .map_err(VorbisError::from_vorbis(VorbisErrorKind::FinishStream))
+        .map_err(Into::into)
+}
+
+// Decodes audio packets from Vorbis stream data into blocks of samples.
+pub(super) struct Decoder<'reader, R: Read> {
+    id_header: IdentHeader,
+    setup_header: SetupHeader,
+    window: PreviousWindowRight,
+    source: &'reader mut Reader<R>,
+    start_pos: u64,
+    stream_size: u64,
+    finished: bool,
+}
+
+impl<'reader, R: Read> Decoder<'reader, R> {
+    pub(super) fn new(
+        info: &StreamInfo,
+        source: &'reader mut Reader<R>,
+    ) -> Result<Self, VorbisError> {
+        // The stream should have contained the CRC32 of a setup header in a header chunk.
+        // Otherwise, the stream cannot be decoded correctly.
+        let crc32 = info
+            .vorbis_crc32
+            .ok_or_else(|| VorbisError::new(VorbisErrorKind::MissingCrc32))?;
+
+        // construct headers needed for decoding packets from stream data
+        let (id_header, setup_header) =
+            init_headers(info.sample_rate.get(), info.channels.get(), crc32)?;
+
+        Ok(Self {
+            id_header,
+            setup_header,
+            window: PreviousWindowRight::new(),
+            start_pos: source.position(),
+            stream_size: info.size.get().into(),
+            source,
+            finished: false,
+        })
+    }
+}
+
+impl<R: Read> Decode for Decoder<'_, R> {
+    fn next_block(&mut self) -> Result<Option<Vec<Vec<f32>>>, EncodeError> {
+        if self.finished || self.source.position() - self.start_pos >= self.stream_size {
+            return Ok(None);
+        }
 
-    while source.position() - start_pos < stream_size {
-        let packet_size = source
+        let packet_size = self
+            .source
             .le_u16()
             .map_err(VorbisError::from_read(VorbisErrorKind::ReadPacket))?;
 
         // signals end of stream data
         if packet_size == u16::MIN || packet_size == u16::MAX {
-            break;
+            self.finished = true;
+            return Ok(None);
         }
 
-        let packet = source
+        let packet = self
+            .source
             .take(packet_size as usize)
             .map_err(VorbisError::from_read(VorbisErrorKind::ReadPacket))?;
 
-        let block: Vec<_> =
-            read_audio_packet_generic(&id_header, &setup_header, &packet, &mut window)
-                .map_err(Into::into)
-                .map_err(VorbisError::from_lewton(VorbisErrorKind::DecodePacket))?;
+        read_audio_packet_generic(&self.id_header, &self.setup_header, &packet, &mut self.window)
+            .map(Some)
+            .map_err(Into::into)
+            .map_err(VorbisError::from_lewton(VorbisErrorKind::DecodePacket))
+            .map_err(Into::into)
+    }
+}
 
-        encoder
-            .encode_audio_block(block)
-            .map_err(VorbisError::from_vorbis(VorbisErrorKind::EncodeBlock))?;
+// Decodes audio packets until the data ends or a packet can't be decoded,
+// returning the end of the last decoded packet and the number of samples decoded up to it.
+pub(super) fn decodable_prefix(info: &StreamInfo, data: &[u8]) -> Option<(usize, u32)> {
+    let mut reader = Reader::new(data);
+    let mut decoder = Decoder::new(info, &mut reader).ok()?;
+    let mut end = 0;
+    let mut samples: u32 = 0;
+
+    while let Ok(Some(block)) = decoder.next_block() {
+        end = decoder.source.position();
+        samples = block
+            .first()
+            .map_or(0, Vec::len)
+            .pipe(u32::try_from)
+            .map_or(u32::MAX, |len| samples.saturating_add(len));
     }
 
-    encoder
-        .finish()
-        .map_err(VorbisError::from_vorbis(VorbisErrorKind::FinishStream))
+    Some((usize::try_from(end).ok()?, samples))
+}
+
+impl From<VorbisBitrate> for VorbisBitrateManagementStrategy {
+    fn from(value: VorbisBitrate) -> Self {
+        match value {
+            VorbisBitrate::QualityVbr { target_quality } => Self::QualityVbr { target_quality },
+            VorbisBitrate::Vbr { target_bitrate } => Self::Vbr { target_bitrate },
+            VorbisBitrate::Abr { average_bitrate } => Self::Abr { average_bitrate },
+            VorbisBitrate::ConstrainedAbr { maximum_bitrate } => {
+                Self::ConstrainedAbr { maximum_bitrate }
+            }
+        }
+    }
 }
 
 // default block sizes for FMOD sound banks:
@@ -230,3 +322,45 @@ impl Display for VorbisErrorKind {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::encode;
+    use crate::encode::{Decode, EncodeError, EncodeOptions};
+    use crate::header::Loop;
+    use std::num::{NonZeroU32, NonZeroU8};
+
+    struct Silence(usize);
+
+    impl Decode for Silence {
+        fn next_block(&mut self) -> Result<Option<Vec<Vec<f32>>>, EncodeError> {
+            Ok((self.0 > 0).then(|| {
+                self.0 -= 1;
+                vec![vec![0.0; 1024]; 2]
+            }))
+        }
+    }
+
+    #[test]
+    fn encode_decoded_blocks() {
+        let stream_loop = Loop::parse(0, 0, 4095).unwrap();
+        let options = EncodeOptions::default().comment("TITLE", "silence");
+
+        let data = encode(
+            NonZeroU32::new(44100).unwrap(),
+            NonZeroU8::new(2).unwrap(),
+            Some(stream_loop),
+            &options,
+            Silence(4),
+            Vec::new(),
+        )
+        .unwrap();
+
+        let contains = |needle: &[u8]| data.windows(needle.len()).any(|window| window == needle);
+
+        assert!(data.starts_with(b"OggS"));
+        assert!(contains(b"LOOPSTART=0"));
+        assert!(contains(b"LOOPLENGTH=4096"));
+        assert!(contains(b"TITLE=silence"));
+    }
+}
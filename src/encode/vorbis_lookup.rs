@@ -19908,7 +19908,7 @@ const FVS_1C08A6FF: [u8; 3189] = [
     0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x04,0x04,0x04,0x00,0x00,0x00,0x00,0x00,0x02,0x00,0x00,0x00,0x04,0x04,
 ];
 
-pub(super) const VORBIS_LOOKUP: Map<u32, &'static [u8]> = phf_map! {
+pub(crate) const VORBIS_LOOKUP: Map<u32, &'static [u8]> = phf_map! {
     0xA722_97FFu32 => &FVS_A72297FF,
     0x69EF_6302u32 => &FVS_69EF6302,
     0x3660_A305u32 => &FVS_3660A305,
@@ -0,0 +1,26 @@
+//! Sound banks built for tests and benchmarks.
+//!
+//! This file is also included by the benchmarks, which can't use items that only exist in test builds.
+
+// Builds the file and stream headers of a PCM16 sound bank with mono streams of the given sizes,
+// each containing `size / 2` samples. Stream data, which follows the headers, is left to the caller.
+pub(crate) fn pcm16_headers(sizes: &[u32]) -> Vec<u8> {
+    let num_streams = u32::try_from(sizes.len()).unwrap();
+    let total_size: u32 = sizes.iter().sum();
+
+    let mut data = Vec::from(*b"FSB5");
+    for field in [1, num_streams, 8 * num_streams, 0, total_size, 2] {
+        data.extend_from_slice(&u32::to_le_bytes(field));
+    }
+    data.resize(60, 0);
+
+    let mut offset = 0;
+    for size in sizes {
+        // sample rate flag 8 (44100 Hz), 1 channel, offset in units of 32 bytes
+        let header = 8 << 1 | u64::from(offset / 32) << 7 | u64::from(size / 2) << 34;
+        data.extend_from_slice(&header.to_le_bytes());
+        offset += size;
+    }
+
+    data
+}
@@ -0,0 +1,102 @@
+use std::num::NonZeroU32;
+
+/// Information from the file header of a sound bank, returned from [`Bank::header`].
+///
+/// [`Bank::header`]: crate::Bank::header
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BankHeader {
+    pub(crate) version: u32,
+    pub(crate) flags: BankFlags,
+    pub(crate) hash: [u8; 16],
+    pub(crate) stream_headers_size: u32,
+    pub(crate) name_table_size: u32,
+    pub(crate) total_stream_size: NonZeroU32,
+}
+
+impl BankHeader {
+    /// Returns the version of the FSB5 format used by the sound bank.
+    ///
+    /// The only known versions are 0 and 1.
+    #[must_use]
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Returns the encoding flags of the sound bank.
+    ///
+    /// Version 0 sound banks don't store encoding flags, so no flags are set for them.
+    #[must_use]
+    pub fn flags(&self) -> BankFlags {
+        self.flags
+    }
+
+    /// Returns the 16 bytes following the encoding flags, which FMOD fills with a hash of the sound bank.
+    ///
+    /// The hash can be used to identify sound banks, but the way it is computed is unknown.
+    #[must_use]
+    pub fn hash(&self) -> [u8; 16] {
+        self.hash
+    }
+
+    /// Returns the total size of all stream headers (including their chunks), in bytes.
+    #[must_use]
+    pub fn stream_headers_size(&self) -> u32 {
+        self.stream_headers_size
+    }
+
+    /// Returns the size of the name table, in bytes.
+    ///
+    /// This is 0 if streams in the sound bank don't have names.
+    #[must_use]
+    pub fn name_table_size(&self) -> u32 {
+        self.name_table_size
+    }
+
+    /// Returns the total size of all stream data, in bytes.
+    #[must_use]
+    pub fn total_stream_size(&self) -> NonZeroU32 {
+        self.total_stream_size
+    }
+}
+
+/// A set of encoding flags stored in the file header of a sound bank.
+///
+/// Flags that aren't recognized by this crate are preserved, and available through [`BankFlags::bits`].
+///
+/// # Examples
+///
+/// ```
+/// use fsbex::BankFlags;
+///
+/// let flags = BankFlags::from_bits(0x01);
+/// assert!(flags.contains(BankFlags::BIG_ENDIAN_PCM));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BankFlags(u32);
+
+impl BankFlags {
+    /// PCM samples are stored in big-endian byte order instead of little-endian.
+    ///
+    /// This is currently only respected for [`AudioFormat::Pcm16`] streams.
+    ///
+    /// [`AudioFormat::Pcm16`]: crate::AudioFormat::Pcm16
+    pub const BIG_ENDIAN_PCM: Self = Self(0x01);
+
+    /// Creates a set of flags from their raw value in the file header.
+    #[must_use]
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    /// Returns the raw value of the flags in the file header.
+    #[must_use]
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Returns `true` if all flags in `other` are set.
+    #[must_use]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
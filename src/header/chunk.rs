@@ -0,0 +1,151 @@
+use super::error::{ChunkError, ChunkErrorKind};
+use super::{Loop, StreamChunkKind};
+use crate::read::Reader;
+use std::num::{NonZeroU32, NonZeroU8};
+use tap::Pipe;
+
+/// A chunk of metadata stored in a stream header.
+///
+/// Chunks that are understood by this crate are returned as typed variants.
+/// All other chunks, including chunks of unknown types, are returned as [`Chunk::Raw`].
+/// This includes comments that are not valid UTF-8 and peak volumes that are too short to be read.
+///
+/// See [`Stream::chunks`] for more information.
+///
+/// [`Stream::chunks`]: crate::Stream::chunks
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Chunk<'data> {
+    /// The number of channels in the stream, overriding the value in the stream header.
+    Channels(NonZeroU8),
+    /// The sample rate (Hz) of the stream, overriding the value in the stream header.
+    SampleRate(NonZeroU32),
+    /// Loop information of the stream.
+    Loop(Loop),
+    /// A comment attached to the stream when the sound bank was built.
+    Comment(&'data str),
+    /// Seek table of a Vorbis stream.
+    VorbisSeekTable {
+        /// The CRC32 checksum of the stream's Vorbis setup header.
+        crc32: u32,
+        /// The raw seek table entries following the checksum.
+        seek_table: &'data [u8],
+    },
+    /// The number of layers per channel in a Vorbis stream.
+    VorbisIntraLayers(NonZeroU8),
+    /// The peak volume of the stream, computed by FMOD when the sound bank was built.
+    PeakVolume(f32),
+    /// A chunk that is not parsed by this crate.
+    Raw {
+        /// The chunk type flag.
+        kind: u8,
+        /// The contents of the chunk.
+        data: &'data [u8],
+    },
+}
+
+impl<'data> Chunk<'data> {
+    // `position` is the position of the chunk data in the file, used for error reporting.
+    pub(super) fn parse(
+        kind: StreamChunkKind,
+        flag: u8,
+        data: &'data [u8],
+        index: u32,
+        position: u64,
+    ) -> Result<Self, ChunkError> {
+        let mut reader = Reader::with_position(data, position);
+
+        let chunk = match kind {
+            StreamChunkKind::Channels => reader
+                .u8()
+                .map_err(ChunkError::factory(index, ChunkErrorKind::ChannelCount))?
+                .pipe(NonZeroU8::new)
+                .ok_or_else(|| ChunkError::new(index, ChunkErrorKind::ZeroChannels))?
+                .pipe(Self::Channels),
+            StreamChunkKind::SampleRate => reader
+                .le_u32()
+                .map_err(ChunkError::factory(index, ChunkErrorKind::SampleRate))?
+                .pipe(NonZeroU32::new)
+                .ok_or_else(|| ChunkError::new(index, ChunkErrorKind::ZeroSampleRate))?
+                .pipe(Self::SampleRate),
+            StreamChunkKind::Loop => {
+                let start = reader
+                    .le_u32()
+                    .map_err(ChunkError::factory(index, ChunkErrorKind::LoopStart))?;
+
+                let end = reader
+                    .le_u32()
+                    .map_err(ChunkError::factory(index, ChunkErrorKind::LoopEnd))?;
+
+                Self::Loop(Loop::parse(index, start, end)?)
+            }
+            StreamChunkKind::Comment => {
+                // comments may be null-terminated, in which case the terminator and any padding are removed
+                let len = data.iter().position(|byte| *byte == 0).unwrap_or(data.len());
+
+                // Comments are optional, so comments that aren't valid UTF-8 are kept as raw data
+                // instead of failing to parse the sound bank.
+                std::str::from_utf8(&data[..len])
+                    .map_or(Self::Raw { kind: flag, data }, Self::Comment)
+            }
+            StreamChunkKind::VorbisSeekTable => {
+                // Vorbis is a variable bitrate codec, so seek tables are used to seek to specific times.
+                // This chunk starts with the CRC32 checksum of a Vorbis setup header.
+                // When encoding this stream, the checksum is used to recover the original setup header.
+
+                let crc32 = reader
+                    .le_u32()
+                    .map_err(ChunkError::factory(index, ChunkErrorKind::VorbisCrc32))?;
+
+                Self::VorbisSeekTable {
+                    crc32,
+                    seek_table: &data[4..],
+                }
+            }
+            StreamChunkKind::VorbisIntraLayers => {
+                // Some Vorbis stream data is stored as multiple "layers" per channel.
+
+                let layers = reader
+                    .le_u32()
+                    .map_err(ChunkError::factory(index, ChunkErrorKind::VorbisLayerCount))?;
+
+                layers
+                    .pipe(u8::try_from)
+                    .map_err(|_| {
+                        ChunkError::new(index, ChunkErrorKind::TooManyVorbisLayers { layers })
+                    })?
+                    .pipe(NonZeroU8::new)
+                    .ok_or_else(|| ChunkError::new(index, ChunkErrorKind::ZeroVorbisLayers))?
+                    .pipe(Self::VorbisIntraLayers)
+            }
+            // like comments, peak volumes are optional, so chunks that are too short are kept as raw data
+            StreamChunkKind::PeakVolume => {
+                reader.le_u32().map_or(Self::Raw { kind: flag, data }, |bits| {
+                    Self::PeakVolume(f32::from_bits(bits))
+                })
+            }
+            _ => Self::Raw { kind: flag, data },
+        };
+
+        Ok(chunk)
+    }
+}
+
+// A stream header chunk as it is stored in the file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct RawChunk {
+    pub(crate) flag: u8,
+    pub(crate) data: Box<[u8]>,
+}
+
+impl RawChunk {
+    pub(crate) fn to_chunk(&self) -> Chunk<'_> {
+        // Chunks were already parsed successfully when the sound bank was read, so this shouldn't fail.
+        let kind = StreamChunkKind::from_flag(self.flag).unwrap_or(StreamChunkKind::Unknown);
+
+        Chunk::parse(kind, self.flag, &self.data, 0, 0).unwrap_or(Chunk::Raw {
+            kind: self.flag,
+            data: &self.data,
+        })
+    }
+}
@@ -12,26 +12,99 @@ pub(crate) struct HeaderError {
     source: Option<HeaderErrorSource>,
 }
 
-#[derive(Debug)]
-#[cfg_attr(test, derive(Clone, Copy, PartialEq, Eq))]
-pub(crate) enum HeaderErrorKind {
+/// The kind of error that occurred while parsing the header of a sound bank.
+///
+/// Errors in individual stream headers and names are described further by
+/// [`StreamErrorKind`], [`ChunkErrorKind`] and [`NameErrorKind`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum HeaderErrorKind {
+    /// The file signature (`FSB5`) could not be found.
     Magic,
+    /// Failed to read the file format version.
     Version,
-    UnknownVersion { version: u32 },
+    /// The file format version was not recognized.
+    UnknownVersion {
+        /// The version found in the file.
+        version: u32,
+    },
+    /// Failed to read the number of streams.
     StreamCount,
+    /// The number of streams was 0.
     ZeroStreams,
+    /// Failed to read the size of stream headers.
     StreamHeadersSize,
+    /// Failed to read the size of the name table.
     NameTableSize,
+    /// Failed to read the total size of stream data.
     TotalStreamSize,
+    /// The total size of stream data was 0 bytes.
     ZeroTotalStreamSize,
+    /// Failed to read the audio format flag.
     AudioFormat,
-    UnknownAudioFormat { flag: u32 },
+    /// The audio format flag was not recognized.
+    UnknownAudioFormat {
+        /// The flag found in the file.
+        flag: u32,
+    },
+    /// Failed to read the encoding flags.
     EncodingFlags,
+    /// Failed to read the (unused) metadata bytes.
     Metadata,
+    /// Failed to parse a stream header. See [`StreamErrorKind`] for the cause.
     StreamHeader,
-    ZeroStreamSize { index: u32 },
-    WrongHeaderSize { expected: usize, actual: usize },
+    /// The size of a stream's data was 0 bytes.
+    ZeroStreamSize {
+        /// The index of the stream.
+        index: u32,
+    },
+    /// The data offset of a stream was smaller than the data offset of the previous stream.
+    ///
+    /// If `index` is equal to the number of streams, the total size of stream data
+    /// was smaller than the data offset of the last stream.
+    UnorderedStreamOffset {
+        /// The index of the stream.
+        index: u32,
+    },
+    /// The combined size of the base header and stream headers was different from the size in the file.
+    WrongHeaderSize {
+        /// The size in bytes given by the file.
+        expected: u64,
+        /// The size in bytes that was actually read.
+        actual: u64,
+    },
+    /// Failed to read stream names. See [`NameErrorKind`] for the cause.
     NameTable,
+    /// The number of streams was greater than the limit set by [`Limits::max_streams`].
+    ///
+    /// [`Limits::max_streams`]: crate::Limits::max_streams
+    TooManyStreams {
+        /// The number of streams given by the file header.
+        num_streams: u32,
+        /// The maximum number of streams.
+        limit: u32,
+    },
+    /// The size of a stream's data was greater than the limit set by [`Limits::max_stream_size`].
+    ///
+    /// [`Limits::max_stream_size`]: crate::Limits::max_stream_size
+    StreamTooLarge {
+        /// The index of the stream.
+        index: u32,
+        /// The size of the stream data, in bytes.
+        size: u32,
+        /// The maximum size of stream data, in bytes.
+        limit: u32,
+    },
+    /// Storing the metadata of the sound bank would allocate more memory
+    /// than the limit set by [`Limits::max_allocation`].
+    ///
+    /// [`Limits::max_allocation`]: crate::Limits::max_allocation
+    AllocationLimit {
+        /// The maximum number of bytes to allocate.
+        limit: u64,
+    },
+    /// Failed to seek to the start of a sound bank embedded in a larger source.
+    Seek,
 }
 
 #[derive(Debug)]
@@ -58,12 +131,80 @@ impl HeaderError {
     }
 }
 
-#[cfg(test)]
 impl HeaderError {
     pub(crate) fn kind(&self) -> HeaderErrorKind {
         self.kind
     }
 
+    fn stream_err(&self) -> Option<&StreamError> {
+        match &self.source {
+            Some(HeaderErrorSource::Stream(e)) => Some(e),
+            _ => None,
+        }
+    }
+
+    fn chunk_err(&self) -> Option<&ChunkError> {
+        match &self.stream_err()?.source {
+            Some(StreamErrorSource::Chunk(e)) => Some(e),
+            _ => None,
+        }
+    }
+
+    fn name_err(&self) -> Option<&NameError> {
+        match &self.source {
+            Some(HeaderErrorSource::NameTable(e)) => Some(e),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn stream_kind(&self) -> Option<StreamErrorKind> {
+        self.stream_err().map(|e| e.kind)
+    }
+
+    pub(crate) fn chunk_kind(&self) -> Option<ChunkErrorKind> {
+        self.chunk_err().map(|e| e.kind)
+    }
+
+    pub(crate) fn name_kind(&self) -> Option<NameErrorKind> {
+        self.name_err().map(|e| e.kind)
+    }
+
+    pub(crate) fn stream_index(&self) -> Option<u32> {
+        match self.kind {
+            HeaderErrorKind::ZeroStreamSize { index }
+            | HeaderErrorKind::UnorderedStreamOffset { index }
+            | HeaderErrorKind::StreamTooLarge { index, .. } => Some(index),
+            _ => self
+                .stream_err()
+                .map(|e| e.index)
+                .or_else(|| self.name_err().map(|e| e.index)),
+        }
+    }
+
+    pub(crate) fn chunk_index(&self) -> Option<u32> {
+        self.chunk_err().map(|e| e.index)
+    }
+
+    // the innermost error that occurred while reading data, if any
+    pub(crate) fn read_err(&self) -> Option<&ReadError> {
+        match &self.source {
+            Some(HeaderErrorSource::Read(e)) => Some(e),
+            Some(HeaderErrorSource::Stream(e)) => match &e.source {
+                Some(StreamErrorSource::Read(e)) => Some(e),
+                Some(StreamErrorSource::Chunk(e)) => e.source.as_ref(),
+                None => None,
+            },
+            Some(HeaderErrorSource::NameTable(e)) => match &e.source {
+                Some(NameErrorSource::Read(e)) => Some(e),
+                _ => None,
+            },
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+impl HeaderError {
     pub(crate) fn is_stream_err_kind(&self, kind: StreamErrorKind) -> bool {
         match &self.source {
             Some(HeaderErrorSource::Stream(e)) => e.kind == kind,
@@ -106,10 +247,21 @@ impl Display for HeaderError {
             Metadata => f.write_str("failed to read (unused) metadata bytes"),
             StreamHeader => f.write_str("failed to parse stream header"),
             ZeroStreamSize { index } => f.write_fmt(format_args!("size of data of stream at index {index} was 0 bytes")),
+            UnorderedStreamOffset { index } => f.write_fmt(format_args!("data offset of stream at index {index} was smaller than the previous offset")),
             WrongHeaderSize { expected, actual } => {
                 f.write_fmt(format_args!("total size of base header and stream headers ({actual} bytes) was different from expected ({expected} bytes)"))
             }
-            NameTable => f.write_str("failed to read stream names")
+            NameTable => f.write_str("failed to read stream names"),
+            TooManyStreams { num_streams, limit } => f.write_fmt(format_args!(
+                "number of streams ({num_streams}) was greater than the limit ({limit})"
+            )),
+            StreamTooLarge { index, size, limit } => f.write_fmt(format_args!(
+                "size of data of stream at index {index} ({size} bytes) was greater than the limit ({limit} bytes)"
+            )),
+            AllocationLimit { limit } => f.write_fmt(format_args!(
+                "metadata of sound bank needed more memory than the limit ({limit} bytes)"
+            )),
+            Seek => f.write_str("failed to seek to start of sound bank"),
         }
     }
 }
@@ -134,12 +286,20 @@ pub(crate) struct StreamError {
     source: Option<StreamErrorSource>,
 }
 
-#[derive(Debug)]
-#[cfg_attr(test, derive(Clone, Copy, PartialEq, Eq))]
-pub(crate) enum StreamErrorKind {
+/// The kind of error that occurred while parsing a stream header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum StreamErrorKind {
+    /// Failed to read the stream metadata.
     StreamInfo,
-    UnknownSampleRate { flag: u8 },
+    /// The sample rate flag was not recognized.
+    UnknownSampleRate {
+        /// The flag found in the stream header.
+        flag: u8,
+    },
+    /// The number of samples was 0.
     ZeroSamples,
+    /// Failed to parse a stream header chunk. See [`ChunkErrorKind`] for the cause.
     Chunk,
 }
 
@@ -219,24 +379,56 @@ pub(crate) struct ChunkError {
     source: Option<ReadError>,
 }
 
-#[derive(Debug)]
-#[cfg_attr(test, derive(Clone, Copy, PartialEq, Eq))]
-pub(crate) enum ChunkErrorKind {
+/// The kind of error that occurred while parsing a chunk of a stream header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ChunkErrorKind {
+    /// Failed to read the chunk flag.
     Flag,
-    UnknownType { flag: u8 },
+    /// The chunk type flag was not recognized.
+    UnknownType {
+        /// The flag found in the chunk.
+        flag: u8,
+    },
+    /// Failed to read the number of channels.
     ChannelCount,
+    /// The number of channels was 0.
     ZeroChannels,
+    /// Failed to read the sample rate.
     SampleRate,
+    /// The sample rate was 0.
     ZeroSampleRate,
+    /// Failed to read the starting position of the loop.
     LoopStart,
+    /// Failed to read the ending position of the loop.
     LoopEnd,
-    ZeroLengthLoop,
+    /// The loop ended before it started.
+    InvalidLoop {
+        /// The starting position of the loop.
+        start: u32,
+        /// The (inclusive) ending position of the loop.
+        end: u32,
+    },
+    /// Failed to read the DSP coefficients.
     DspCoefficients,
+    /// Failed to read the CRC32 of the Vorbis setup header.
     VorbisCrc32,
+    /// Failed to read the number of layers per channel in a Vorbis stream.
     VorbisLayerCount,
-    TooManyVorbisLayers { layers: u32 },
+    /// The number of layers in a Vorbis stream was greater than 255.
+    TooManyVorbisLayers {
+        /// The number of layers found in the chunk.
+        layers: u32,
+    },
+    /// The number of layers in a Vorbis stream was 0.
     ZeroVorbisLayers,
-    WrongChunkSize { expected: u32, actual: usize },
+    /// The size of the chunk was different from the size given in the chunk flag.
+    WrongChunkSize {
+        /// The size in bytes given by the chunk flag.
+        expected: u32,
+        /// The size in bytes that was actually read.
+        actual: u64,
+    },
 }
 
 impl ChunkError {
@@ -269,6 +461,13 @@ impl ChunkError {
     }
 }
 
+#[cfg(test)]
+impl ChunkError {
+    pub(crate) fn kind(&self) -> ChunkErrorKind {
+        self.kind
+    }
+}
+
 impl Display for ChunkError {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         use ChunkErrorKind::*;
@@ -284,7 +483,9 @@ impl Display for ChunkError {
             ZeroSampleRate => f.write_str("sample rate was 0"),
             LoopStart => f.write_str("failed to read starting position of loop in stream"),
             LoopEnd => f.write_str("failed to read ending position of loop in stream"),
-            ZeroLengthLoop => f.write_str("length of loop in stream was 0"),
+            InvalidLoop { start, end } => f.write_fmt(format_args!(
+                "loop in stream ended before it started (start {start}, end {end})"
+            )),
             DspCoefficients => f.write_str("failed to read DSP coefficients of stream"),
             VorbisCrc32 => f.write_str("failed to read CRC32 of Vorbis setup header"),
             VorbisLayerCount => {
@@ -316,14 +517,32 @@ impl Error for ChunkError {
 pub(crate) struct NameError {
     index: u32,
     kind: NameErrorKind,
-    source: NameErrorSource,
+    source: Option<NameErrorSource>,
 }
 
-#[derive(Debug)]
-pub(crate) enum NameErrorKind {
+/// The kind of error that occurred while reading stream names.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum NameErrorKind {
+    /// Failed to read the offset of a stream name.
     NameOffset,
+    /// Failed to read a stream name.
     Name,
+    /// A stream name was not valid UTF-8.
     Utf8,
+    /// The space given to a stream name in the name table was larger
+    /// than the limit set by [`Limits::max_name_len`].
+    ///
+    /// [`Limits::max_name_len`]: crate::Limits::max_name_len
+    TooLong {
+        /// The size of the name (including the null terminator), in bytes.
+        len: u32,
+        /// The maximum size of a name, in bytes.
+        limit: u32,
+    },
+    /// The offset of a stream name was greater than the offset of the next name,
+    /// or greater than the size of the name table for the last name.
+    UnorderedNameOffset,
 }
 
 #[derive(Debug)]
@@ -334,24 +553,36 @@ enum NameErrorSource {
 }
 
 impl NameError {
-    fn new(index: u32, kind: NameErrorKind, source: NameErrorSource) -> Self {
+    pub(crate) fn new(index: u32, kind: NameErrorKind) -> Self {
         Self {
             index,
             kind,
-            source,
+            source: None,
+        }
+    }
+
+    fn new_with_source(index: u32, kind: NameErrorKind, source: NameErrorSource) -> Self {
+        Self {
+            index,
+            kind,
+            source: Some(source),
         }
     }
 
     pub(crate) fn read_factory(index: u32, kind: NameErrorKind) -> impl FnOnce(ReadError) -> Self {
-        move |source| Self::new(index, kind, NameErrorSource::Read(source))
+        move |source| Self::new_with_source(index, kind, NameErrorSource::Read(source))
     }
 
     pub(crate) fn cstr_factory(index: u32) -> impl FnOnce(FromBytesUntilNulError) -> Self {
-        move |source| Self::new(index, NameErrorKind::Name, NameErrorSource::CStr(source))
+        move |source| {
+            Self::new_with_source(index, NameErrorKind::Name, NameErrorSource::CStr(source))
+        }
     }
 
     pub(crate) fn utf8_factory(index: u32) -> impl FnOnce(Utf8Error) -> Self {
-        move |source| Self::new(index, NameErrorKind::Utf8, NameErrorSource::Utf8(source))
+        move |source| {
+            Self::new_with_source(index, NameErrorKind::Utf8, NameErrorSource::Utf8(source))
+        }
     }
 }
 
@@ -372,6 +603,12 @@ impl Display for NameError {
             NameOffset => f.write_str("failed to read offset of stream name"),
             Name => f.write_str("failed to read stream name"),
             Utf8 => f.write_str("stream name was not valid UTF-8"),
+            TooLong { len, limit } => f.write_fmt(format_args!(
+                "size of stream name ({len} bytes) was greater than the limit ({limit} bytes)"
+            )),
+            UnorderedNameOffset => {
+                f.write_str("offset of stream name was greater than the next offset")
+            }
         }?;
 
         f.write_fmt(format_args!(" - stream name at index {}", self.index))
@@ -381,9 +618,10 @@ impl Display for NameError {
 impl Error for NameError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match &self.source {
-            NameErrorSource::Read(e) => Some(e),
-            NameErrorSource::CStr(e) => Some(e),
-            NameErrorSource::Utf8(e) => Some(e),
+            Some(NameErrorSource::Read(e)) => Some(e),
+            Some(NameErrorSource::CStr(e)) => Some(e),
+            Some(NameErrorSource::Utf8(e)) => Some(e),
+            None => None,
         }
     }
 }
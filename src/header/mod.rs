@@ -1,29 +1,53 @@
 use crate::read::Reader;
+use crate::stream::StreamView;
+mod bank_header;
+mod chunk;
 pub(crate) mod error;
+pub(crate) mod options;
+pub use bank_header::{BankFlags, BankHeader};
 use bilge::prelude::*;
+pub use chunk::Chunk;
+pub(crate) use chunk::RawChunk;
 use error::{
     ChunkError, ChunkErrorKind, HeaderError, HeaderErrorKind, NameError, NameErrorKind,
     StreamError, StreamErrorKind,
 };
+use options::{Diagnostics, ParseWarning};
 use std::{
     ffi::CStr,
     fmt::{Display, Formatter, Result as FmtResult},
     io::Read,
     iter::zip,
+    mem::size_of,
     num::{NonZeroU32, NonZeroU8},
-    ops::Mul,
+    ops::Range,
+    time::Duration,
 };
 use tap::Pipe;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub(crate) struct Header {
     pub(crate) format: AudioFormat,
-    pub(crate) flags: u32,
+    pub(crate) base: BankHeader,
     pub(crate) stream_info: Box<[StreamInfo]>,
+    pub(crate) warnings: Box<[ParseWarning]>,
+    // the number of bytes read from the name table, which is needed to validate the sound bank
+    pub(crate) name_table_read: u64,
 }
 
 impl Header {
+    #[cfg(test)]
     pub(crate) fn parse<R: Read>(reader: &mut Reader<R>) -> Result<Self, HeaderError> {
+        Self::parse_with(reader, Diagnostics::default())
+    }
+
+    pub(crate) fn parse_with<R: Read>(
+        reader: &mut Reader<R>,
+        mut diagnostics: Diagnostics,
+    ) -> Result<Self, HeaderError> {
+        // The sound bank may start partway through the reader, so sizes are measured from here.
+        let start = reader.position();
+
         // check for file signature
         match reader.take_const() {
             Ok(data) if data == FSB5_MAGIC => Ok(()),
@@ -32,10 +56,10 @@ impl Header {
         }?;
 
         // determines how encoding flags are read
-        let version = reader
+        let raw_version = reader
             .le_u32()
-            .map_err(HeaderError::factory(HeaderErrorKind::Version))?
-            .try_into()?;
+            .map_err(HeaderError::factory(HeaderErrorKind::Version))?;
+        let version = Version::try_from(raw_version)?;
 
         let num_streams = reader
             .le_u32()
@@ -63,37 +87,48 @@ impl Header {
             .and_then(AudioFormat::parse)?;
 
         // read encoding flags
-        let (flags, base_header_size) = match version {
-            Version::V0 => (0, 64),
+        let flags = match version {
+            Version::V0 => 0,
             Version::V1 => {
                 reader
                     .skip(4)
                     .map_err(HeaderError::factory(HeaderErrorKind::EncodingFlags))?;
 
-                let flags = reader
+                reader
                     .le_u32()
-                    .map_err(HeaderError::factory(HeaderErrorKind::EncodingFlags))?;
-
-                (flags, 60)
+                    .map_err(HeaderError::factory(HeaderErrorKind::EncodingFlags))?
             }
         };
+        let base_header_size = version.base_header_size();
 
-        // skip unknown header data
-        reader
-            .advance_to(base_header_size)
-            .map_err(HeaderError::factory(HeaderErrorKind::Metadata))?;
+        let hash = read_hash(reader, start, base_header_size)?;
 
-        let mut stream_info = parse_stream_headers(reader, num_streams, total_stream_size)?;
+        let mut stream_info =
+            parse_stream_headers(reader, num_streams, total_stream_size, &mut diagnostics)?;
 
-        let header_size = base_header_size + stream_headers_size as usize;
+        let header_size = base_header_size + u64::from(stream_headers_size);
+        let actual = reader.position() - start;
 
         // make sure base header + stream headers have been read
-        reader.advance_to(header_size).map_err(HeaderError::factory(
-            HeaderErrorKind::WrongHeaderSize {
-                expected: header_size,
-                actual: reader.position(),
-            },
-        ))?;
+        if actual > header_size {
+            diagnostics.recover(
+                HeaderError::new(HeaderErrorKind::WrongHeaderSize {
+                    expected: header_size,
+                    actual,
+                }),
+                ParseWarning::WrongHeaderSize {
+                    expected: header_size,
+                    actual,
+                },
+            )?;
+        } else {
+            reader.advance_to(start + header_size).map_err(HeaderError::factory(
+                HeaderErrorKind::WrongHeaderSize {
+                    expected: header_size,
+                    actual,
+                },
+            ))?;
+        }
 
         // Read stream names, if present.
         // The name table has two parts: name offsets, then names (stored as null-terminated strings).
@@ -101,36 +136,112 @@ impl Header {
         // for example, if the first name offset is 0 and the second name offset is 12,
         // then the first name's length (including the null terminator) is 12 - 0 = 12.
         // The final name offset is subtracted from the name table size to get the final name's length.
-        if name_table_size != 0 {
-            let mut name_offsets = Vec::with_capacity(num_streams.get() as usize + 1);
+        let name_table_start = reader.position();
 
-            for index in 0..num_streams.get() {
-                let offset = reader
-                    .le_u32()
-                    .map_err(NameError::read_factory(index, NameErrorKind::NameOffset))?;
+        if name_table_size != 0 {
+            diagnostics.allocate(name_table_size.into())?;
+
+            read_name_table(
+                reader,
+                num_streams,
+                name_table_size,
+                &mut stream_info,
+                &mut diagnostics,
+            )?;
+        }
 
-                name_offsets.push(offset);
-            }
-            name_offsets.push(name_table_size);
+        // Stream data starts right after the name table, so offsets can now be made absolute.
+        let name_table_read = reader.position() - name_table_start;
+        let data_start = skip_name_table(reader, name_table_start, name_table_size)?;
 
-            read_stream_names(reader, &name_offsets, &mut stream_info)?;
+        for info in &mut stream_info {
+            info.data_offset += data_start;
         }
 
         Ok(Self {
             format,
-            flags,
+            base: BankHeader {
+                version: raw_version,
+                flags: BankFlags::from_bits(flags),
+                hash,
+                stream_headers_size,
+                name_table_size,
+                total_stream_size,
+            },
             stream_info: stream_info.into_boxed_slice(),
+            warnings: diagnostics.into_warnings(),
+            name_table_read,
         })
     }
+
+    // the size of the whole sound bank, computed from the sizes in its file header
+    pub(crate) fn bank_size(&self) -> u64 {
+        let version = Version::try_from(self.base.version)
+            .expect("version was already validated to be known");
+
+        version.base_header_size()
+            + u64::from(self.base.stream_headers_size)
+            + u64::from(self.base.name_table_size)
+            + u64::from(self.base.total_stream_size.get())
+    }
+
+    // used by both `Bank` and `AsyncBank`, so their accessors behave the same
+
+    pub(crate) fn num_streams(&self) -> NonZeroU32 {
+        self.stream_info
+            .len()
+            .pipe(u32::try_from)
+            .ok()
+            .and_then(NonZeroU32::new)
+            .expect("stream count was already validated to be NonZeroU32")
+    }
+
+    pub(crate) fn stream_views(&self) -> impl ExactSizeIterator<Item = StreamView<'_>> + '_ {
+        (0..self.num_streams().get())
+            .zip(self.stream_info.iter())
+            .map(|(index, info)| StreamView::new(index, info))
+    }
 }
 
-const FSB5_MAGIC: [u8; 4] = *b"FSB5";
+// The base header ends with a 16-byte hash, followed by 8 bytes of unknown data.
+fn read_hash<R: Read>(
+    reader: &mut Reader<R>,
+    start: u64,
+    base_header_size: u64,
+) -> Result<[u8; 16], HeaderError> {
+    reader
+        .advance_to(start + base_header_size - 24)
+        .map_err(HeaderError::factory(HeaderErrorKind::Metadata))?;
+
+    let hash = reader
+        .take_const()
+        .map_err(HeaderError::factory(HeaderErrorKind::Metadata))?;
+
+    // skip unknown header data
+    reader
+        .advance_to(start + base_header_size)
+        .map_err(HeaderError::factory(HeaderErrorKind::Metadata))?;
+
+    Ok(hash)
+}
+
+pub(crate) const FSB5_MAGIC: [u8; 4] = *b"FSB5";
 
+#[derive(Clone, Copy)]
 enum Version {
     V0,
     V1,
 }
 
+impl Version {
+    fn base_header_size(self) -> u64 {
+        match self {
+            Self::V0 => 64,
+            Self::V1 => 60,
+        }
+    }
+}
+
 impl TryFrom<u32> for Version {
     type Error = HeaderError;
 
@@ -214,6 +325,17 @@ impl AudioFormat {
             flag => Err(HeaderError::new(HeaderErrorKind::UnknownAudioFormat { flag })),
         }
     }
+
+    // size of a single sample, for formats where every sample has a fixed size
+    pub(crate) fn pcm_byte_depth(self) -> Option<NonZeroU8> {
+        match self {
+            Self::Pcm8 => NonZeroU8::new(1),
+            Self::Pcm16 => NonZeroU8::new(2),
+            Self::Pcm24 => NonZeroU8::new(3),
+            Self::Pcm32 | Self::PcmFloat => NonZeroU8::new(4),
+            _ => None,
+        }
+    }
 }
 
 impl Display for AudioFormat {
@@ -244,26 +366,40 @@ fn parse_stream_headers<R: Read>(
     reader: &mut Reader<R>,
     num_streams: NonZeroU32,
     total_stream_size: NonZeroU32,
+    diagnostics: &mut Diagnostics,
 ) -> Result<Vec<StreamInfo>, HeaderError> {
+    let limit = diagnostics.limits().streams;
+    if num_streams.get() > limit {
+        return Err(HeaderError::new(HeaderErrorKind::TooManyStreams {
+            num_streams: num_streams.get(),
+            limit,
+        }));
+    }
+
     let num_streams_usize = num_streams.get() as usize;
 
-    let mut stream_headers = Vec::with_capacity(num_streams_usize);
-    let mut stream_offsets = Vec::with_capacity(num_streams_usize + 1);
+    // The stream count comes straight from the file header and can't be trusted yet,
+    // so these buffers grow as stream headers are actually read instead of being preallocated.
+    let mut stream_headers = Vec::new();
+    let mut stream_offsets = Vec::new();
 
     for index in 0..num_streams.get() {
         // Stream headers contain information such as sample rate (Hz) and number of channels.
         // They can also contain metadata chunks useful for decoding and encoding stream data.
         // Sometimes, flags for header fields are set to 0 while the actual values are stored in chunks.
         let mut stream_header = match reader.le_u64() {
-            Ok(n) => RawStreamHeader::from(n).parse(index),
+            Ok(n) => RawStreamHeader::from(n).parse(index, diagnostics),
             Err(e) => Err(StreamError::new_with_source(index, StreamErrorKind::StreamInfo, e)),
         }?;
 
         if stream_header.has_chunks {
-            parse_stream_chunks(reader, &mut stream_header)
+            parse_stream_chunks(reader, &mut stream_header, index, diagnostics)
                 .map_err(|e| e.into_stream_err(index))?;
         }
 
+        let chunks_size: usize = stream_header.chunks.iter().map(|chunk| chunk.data.len()).sum();
+        diagnostics.allocate((size_of::<StreamInfo>() + chunks_size) as u64)?;
+
         stream_offsets.push(stream_header.data_offset);
         stream_headers.push(stream_header);
     }
@@ -274,12 +410,21 @@ fn parse_stream_headers<R: Read>(
 
     let mut stream_info = Vec::with_capacity(num_streams_usize);
 
-    for ((size, header), index) in zip(
-        stream_offsets.windows(2).map(|window| window[1] - window[0]),
-        stream_headers,
-    )
-    .zip(0..)
-    {
+    let limit = diagnostics.limits().stream_size;
+
+    for ((window, header), index) in zip(stream_offsets.windows(2), stream_headers).zip(0..) {
+        let size = window[1].checked_sub(window[0]).ok_or_else(|| {
+            HeaderError::new(HeaderErrorKind::UnorderedStreamOffset { index: index + 1 })
+        })?;
+
+        if size > limit {
+            return Err(HeaderError::new(HeaderErrorKind::StreamTooLarge {
+                index,
+                size,
+                limit,
+            }));
+        }
+
         stream_info.push(
             header.with_stream_size(
                 size.try_into()
@@ -312,10 +457,17 @@ struct StreamHeader {
     stream_loop: Option<Loop>,
     dsp_coeffs: Option<Box<[i16]>>,
     vorbis_crc32: Option<u32>,
+    peak_volume: Option<u32>,
+    comment: Option<Box<str>>,
+    chunks: Box<[RawChunk]>,
 }
 
 impl RawStreamHeader {
-    fn parse(self, stream_index: u32) -> Result<StreamHeader, StreamError> {
+    fn parse(
+        self,
+        stream_index: u32,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<StreamHeader, StreamError> {
         let sample_rate = match self.sample_rate().value() {
             0 => Ok(4000),
             1 => Ok(8000),
@@ -328,10 +480,12 @@ impl RawStreamHeader {
             8 => Ok(44100),
             9 => Ok(48000),
             10 => Ok(96000),
-            flag => Err(StreamError::new(
-                stream_index,
-                StreamErrorKind::UnknownSampleRate { flag },
-            )),
+            flag => diagnostics
+                .recover(
+                    StreamError::new(stream_index, StreamErrorKind::UnknownSampleRate { flag }),
+                    ParseWarning::UnknownSampleRate { stream_index, flag },
+                )
+                .map(|()| 44100),
         }?
         .try_into()
         .unwrap();
@@ -363,6 +517,9 @@ impl RawStreamHeader {
             stream_loop: None,
             dsp_coeffs: None,
             vorbis_crc32: None,
+            peak_volume: None,
+            comment: None,
+            chunks: Box::new([]),
         })
     }
 }
@@ -370,121 +527,125 @@ impl RawStreamHeader {
 fn parse_stream_chunks<R: Read>(
     reader: &mut Reader<R>,
     stream: &mut StreamHeader,
+    stream_index: u32,
+    diagnostics: &mut Diagnostics,
 ) -> Result<(), ChunkError> {
-    use crate::header::Loop;
-    use StreamChunkKind::*;
+    let mut chunks = Vec::new();
 
     for index in 0.. {
-        let chunk = match reader.le_u32() {
-            Ok(n) => RawStreamChunk::from(n).parse(index),
-            Err(e) => Err(ChunkError::new_with_source(index, ChunkErrorKind::Flag, e)),
-        }?;
-
-        let start_position = reader.position();
-
-        match chunk.kind {
-            Channels => {
-                stream.channels = reader
-                    .u8()
-                    .map_err(ChunkError::factory(index, ChunkErrorKind::ChannelCount))?
-                    .try_into()
-                    .map_err(|_| ChunkError::new(index, ChunkErrorKind::ZeroChannels))?;
-            }
-            SampleRate => {
-                stream.sample_rate = reader
-                    .le_u32()
-                    .map_err(ChunkError::factory(index, ChunkErrorKind::SampleRate))?
-                    .try_into()
-                    .map_err(|_| ChunkError::new(index, ChunkErrorKind::ZeroSampleRate))?;
+        let raw_chunk = reader
+            .le_u32()
+            .map(RawStreamChunk::from)
+            .map_err(ChunkError::factory(index, ChunkErrorKind::Flag))?;
+
+        let flag = raw_chunk.kind().value();
+
+        // unknown chunks are kept as raw data in non-strict mode
+        let chunk = match raw_chunk.parse(index) {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                diagnostics.recover(
+                    e,
+                    ParseWarning::UnknownChunk {
+                        stream_index,
+                        chunk_index: index,
+                        flag,
+                    },
+                )?;
+
+                StreamChunk {
+                    more_chunks: raw_chunk.more_chunks(),
+                    size: raw_chunk.size().value(),
+                    kind: StreamChunkKind::Unknown,
+                }
             }
-            Loop => {
-                let start = reader
-                    .le_u32()
-                    .map_err(ChunkError::factory(index, ChunkErrorKind::LoopStart))?;
+        };
 
-                let end = reader
-                    .le_u32()
-                    .map_err(ChunkError::factory(index, ChunkErrorKind::LoopEnd))?;
+        // The entire chunk is read before parsing, so reading never continues past its declared size.
+        let start_position = reader.position();
 
-                stream.stream_loop = Some(Loop::parse(index, start, end)?);
+        let data = reader.take(chunk.size as usize).map_err(|e| {
+            let kind = ChunkErrorKind::WrongChunkSize {
+                expected: chunk.size,
+                actual: e.position() - start_position,
+            };
+            ChunkError::new_with_source(index, kind, e)
+        })?;
+
+        if let StreamChunkKind::DspCoefficients = chunk.kind {
+            let dsp_coeffs = parse_dsp_coefficients(&data, stream.channels, index, start_position)?;
+            stream.dsp_coeffs = Some(dsp_coeffs);
+        } else {
+            match Chunk::parse(chunk.kind, flag, &data, index, start_position)? {
+                Chunk::Channels(channels) => stream.channels = channels,
+                Chunk::SampleRate(sample_rate) => stream.sample_rate = sample_rate,
+                Chunk::Loop(stream_loop) => stream.stream_loop = Some(stream_loop),
+                Chunk::Comment(comment) => stream.comment = Some(comment.into()),
+                // The seek table is discarded because it isn't useful for stream decoding or encoding.
+                Chunk::VorbisSeekTable { crc32, .. } => stream.vorbis_crc32 = Some(crc32),
+                Chunk::VorbisIntraLayers(layers) => {
+                    // For decoding and encoding purposes, layers simply mean that more channels are present.
+                    stream.channels = stream.channels.checked_mul(layers).ok_or_else(|| {
+                        ChunkError::new(
+                            index,
+                            ChunkErrorKind::TooManyVorbisLayers {
+                                layers: layers.get().into(),
+                            },
+                        )
+                    })?;
+                }
+                // stored as bits so that stream information can be compared for equality
+                Chunk::PeakVolume(peak_volume) => stream.peak_volume = Some(peak_volume.to_bits()),
+                Chunk::Raw { .. } => {}
             }
-            DspCoefficients => {
-                // used for decoding and encoding GC ADPCM streams
-
-                let channels = stream.channels.get();
-
-                let mut dsp_coeffs = Vec::with_capacity(channels as usize);
-
-                for _ in 0..channels {
-                    let mut coeff = 0;
-
-                    for _ in 0..16 {
-                        coeff += reader
-                            .be_i16()
-                            .map_err(ChunkError::factory(index, ChunkErrorKind::DspCoefficients))?;
-                    }
+        }
 
-                    reader
-                        .skip(14)
-                        .map_err(ChunkError::factory(index, ChunkErrorKind::DspCoefficients))?;
+        chunks.push(RawChunk {
+            flag,
+            data: data.into_boxed_slice(),
+        });
 
-                    dsp_coeffs.push(coeff);
-                }
+        if !chunk.more_chunks {
+            break;
+        }
+    }
 
-                stream.dsp_coeffs = Some(dsp_coeffs.into_boxed_slice());
-            }
-            VorbisSeekTable => {
-                // Vorbis is a variable bitrate codec, so seek tables are used to seek to specific times.
-                // This chunk starts with the CRC32 checksum of a Vorbis setup header.
-                // When encoding this stream, the checksum is used to recover the original setup header.
-                // The seek table is discarded because it isn't useful for stream decoding or encoding.
+    stream.chunks = chunks.into_boxed_slice();
 
-                stream.vorbis_crc32 = reader
-                    .le_u32()
-                    .map_err(ChunkError::factory(index, ChunkErrorKind::VorbisCrc32))?
-                    .pipe(Some);
-            }
-            VorbisIntraLayers => {
-                // Some Vorbis stream data is stored as multiple "layers" per channel.
-                // For decoding and encoding purposes, layers simply mean that more channels are present.
+    Ok(())
+}
 
-                let layers = reader
-                    .le_u32()
-                    .map_err(ChunkError::factory(index, ChunkErrorKind::VorbisLayerCount))?;
-
-                stream.channels = layers
-                    .pipe(u8::try_from)
-                    .map_err(|_| {
-                        ChunkError::new(index, ChunkErrorKind::TooManyVorbisLayers { layers })
-                    })?
-                    .mul(stream.channels.get())
-                    .try_into()
-                    .map_err(|_| ChunkError::new(index, ChunkErrorKind::ZeroVorbisLayers))?;
-            }
-            _ => {}
+// used for decoding and encoding GC ADPCM streams
+fn parse_dsp_coefficients(
+    data: &[u8],
+    channels: NonZeroU8,
+    index: u32,
+    position: u64,
+) -> Result<Box<[i16]>, ChunkError> {
+    let mut reader = Reader::with_position(data, position);
+    let mut dsp_coeffs = Vec::with_capacity(channels.get() as usize);
+
+    for _ in 0..channels.get() {
+        let mut coeff = 0;
+
+        for _ in 0..16 {
+            coeff += reader
+                .be_i16()
+                .map_err(ChunkError::factory(index, ChunkErrorKind::DspCoefficients))?;
         }
 
-        // make sure the entire chunk has been read before continuing
         reader
-            .advance_to(start_position + chunk.size as usize)
-            .map_err(ChunkError::factory(
-                index,
-                ChunkErrorKind::WrongChunkSize {
-                    expected: chunk.size,
-                    actual: reader.position() - start_position,
-                },
-            ))?;
+            .skip(14)
+            .map_err(ChunkError::factory(index, ChunkErrorKind::DspCoefficients))?;
 
-        if !chunk.more_chunks {
-            break;
-        }
+        dsp_coeffs.push(coeff);
     }
 
-    Ok(())
+    Ok(dsp_coeffs.into_boxed_slice())
 }
 
 #[bitsize(32)]
-#[derive(FromBits)]
+#[derive(Clone, Copy, FromBits)]
 struct RawStreamChunk {
     more_chunks: bool,
     size: u24,
@@ -497,6 +658,7 @@ struct StreamChunk {
     kind: StreamChunkKind,
 }
 
+#[derive(Clone, Copy)]
 enum StreamChunkKind {
     Channels,
     SampleRate,
@@ -510,27 +672,37 @@ enum StreamChunkKind {
     PeakVolume,
     VorbisIntraLayers,
     OpusDataSize,
+    Unknown,
+}
+
+impl StreamChunkKind {
+    fn from_flag(flag: u8) -> Option<Self> {
+        use StreamChunkKind::*;
+
+        match flag {
+            1 => Some(Channels),
+            2 => Some(SampleRate),
+            3 => Some(Loop),
+            4 => Some(Comment),
+            6 => Some(XmaSeekTable),
+            7 => Some(DspCoefficients),
+            9 => Some(Atrac9Config),
+            10 => Some(XwmaConfig),
+            11 => Some(VorbisSeekTable),
+            13 => Some(PeakVolume),
+            14 => Some(VorbisIntraLayers),
+            15 => Some(OpusDataSize),
+            _ => None,
+        }
+    }
 }
 
 impl RawStreamChunk {
     fn parse(self, chunk_index: u32) -> Result<StreamChunk, ChunkError> {
-        use StreamChunkKind::*;
+        let flag = self.kind().value();
 
-        let kind = match self.kind().value() {
-            1 => Ok(Channels),
-            2 => Ok(SampleRate),
-            3 => Ok(Loop),
-            4 => Ok(Comment),
-            6 => Ok(XmaSeekTable),
-            7 => Ok(DspCoefficients),
-            9 => Ok(Atrac9Config),
-            10 => Ok(XwmaConfig),
-            11 => Ok(VorbisSeekTable),
-            13 => Ok(PeakVolume),
-            14 => Ok(VorbisIntraLayers),
-            15 => Ok(OpusDataSize),
-            flag => Err(ChunkError::new(chunk_index, ChunkErrorKind::UnknownType { flag })),
-        }?;
+        let kind = StreamChunkKind::from_flag(flag)
+            .ok_or_else(|| ChunkError::new(chunk_index, ChunkErrorKind::UnknownType { flag }))?;
 
         Ok(StreamChunk {
             more_chunks: self.more_chunks(),
@@ -541,6 +713,10 @@ impl RawStreamChunk {
 }
 
 /// Loop information associated with a stream.
+///
+/// All positions are measured in samples (per channel) from the start of the stream,
+/// independent of the stream's [`AudioFormat`] and number of channels.
+/// Conversions to other units are provided by [`Loop::start_time`], [`Loop::end_time`] and [`Loop::byte_range`].
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Loop {
     start: u32,
@@ -548,22 +724,26 @@ pub struct Loop {
 }
 
 impl Loop {
-    fn parse(index: u32, start: u32, end: u32) -> Result<Self, ChunkError> {
-        let len = NonZeroU32::new(end - start)
-            .ok_or_else(|| ChunkError::new(index, ChunkErrorKind::ZeroLengthLoop))?;
+    // Sound banks store the position of the last sample in the loop (i.e. the end is inclusive).
+    pub(crate) fn parse(index: u32, start: u32, end: u32) -> Result<Self, ChunkError> {
+        let len = end
+            .checked_add(1)
+            .and_then(|end| end.checked_sub(start))
+            .and_then(NonZeroU32::new)
+            .ok_or_else(|| ChunkError::new(index, ChunkErrorKind::InvalidLoop { start, end }))?;
 
         Ok(Self { start, len })
     }
 
-    /// Returns the starting position of the loop.
-    /// This value refers to the offset, in bytes, from the start of the stream data.
+    /// Returns the starting position of the loop, in samples.
+    /// This is the position of the first sample in the loop.
     #[must_use]
     pub fn start(&self) -> u32 {
         self.start
     }
 
-    /// Returns the ending position of the loop.
-    /// This value refers to the offset, in bytes, from the start of the stream data.
+    /// Returns the ending position of the loop, in samples.
+    /// This is the position one past the last sample in the loop, so `end - start == len`.
     #[allow(clippy::missing_panics_doc)]
     #[must_use]
     pub fn end(&self) -> NonZeroU32 {
@@ -572,11 +752,50 @@ impl Loop {
             .expect("the sum of u32 and NonZeroU32 must be NonZeroU32")
     }
 
-    /// Returns the length of the loop, in bytes.
+    /// Returns the length of the loop, in samples.
     #[must_use]
     pub fn len(&self) -> NonZeroU32 {
         self.len
     }
+
+    /// Returns `true` if the loop lies entirely within a stream with `sample_count` samples.
+    #[must_use]
+    pub fn is_within(&self, sample_count: NonZeroU32) -> bool {
+        self.end() <= sample_count
+    }
+
+    /// Returns the starting position of the loop as a time offset from the start of the stream.
+    #[must_use]
+    pub fn start_time(&self, sample_rate: NonZeroU32) -> Duration {
+        samples_to_duration(self.start.into(), sample_rate)
+    }
+
+    /// Returns the ending position of the loop as a time offset from the start of the stream.
+    #[must_use]
+    pub fn end_time(&self, sample_rate: NonZeroU32) -> Duration {
+        samples_to_duration(self.end().get().into(), sample_rate)
+    }
+
+    /// Returns the range of bytes covered by the loop within the stream data.
+    ///
+    /// Returns `None` if samples of the audio format don't have a fixed size.
+    /// This is the case for all formats except PCM.
+    #[must_use]
+    pub fn byte_range(&self, format: AudioFormat, channels: NonZeroU8) -> Option<Range<u64>> {
+        let frame_size = u64::from(format.pcm_byte_depth()?.get()) * u64::from(channels.get());
+
+        Some(u64::from(self.start) * frame_size..u64::from(self.end().get()) * frame_size)
+    }
+}
+
+pub(crate) fn samples_to_duration(samples: u64, sample_rate: NonZeroU32) -> Duration {
+    let sample_rate = u64::from(sample_rate.get());
+
+    // whole seconds and the remaining samples are converted separately to avoid overflow
+    let secs = samples / sample_rate;
+    let nanos = (samples % sample_rate) * 1_000_000_000 / sample_rate;
+
+    Duration::new(secs, nanos.try_into().expect("remainder is less than one second"))
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -587,10 +806,21 @@ pub(crate) struct StreamInfo {
     pub(crate) stream_loop: Option<Loop>,
     pub(crate) _dsp_coeffs: Option<Box<[i16]>>,
     pub(crate) vorbis_crc32: Option<u32>,
+    pub(crate) peak_volume: Option<u32>,
+    pub(crate) comment: Option<Box<str>>,
     pub(crate) size: NonZeroU32,
+    // the position of the stream data within the file, which is only known once the name table is read
+    pub(crate) data_offset: u64,
+    pub(crate) chunks: Box<[RawChunk]>,
     pub(crate) name: Option<Box<str>>,
 }
 
+impl StreamInfo {
+    pub(crate) fn data_range(&self) -> Range<u64> {
+        self.data_offset..self.data_offset + u64::from(self.size.get())
+    }
+}
+
 impl StreamHeader {
     fn with_stream_size(self, size: NonZeroU32) -> StreamInfo {
         // The stream name is read from the name table (if it exists), so its value is set to None for now.
@@ -601,38 +831,138 @@ impl StreamHeader {
             stream_loop: self.stream_loop,
             _dsp_coeffs: self.dsp_coeffs,
             vorbis_crc32: self.vorbis_crc32,
+            peak_volume: self.peak_volume,
+            comment: self.comment,
             size,
+            data_offset: self.data_offset.into(),
+            chunks: self.chunks,
             name: None,
         }
     }
 }
 
+fn read_name_table<R: Read>(
+    reader: &mut Reader<R>,
+    num_streams: NonZeroU32,
+    name_table_size: u32,
+    stream_info: &mut [StreamInfo],
+    diagnostics: &mut Diagnostics,
+) -> Result<(), NameError> {
+    let mut name_offsets = Vec::with_capacity(num_streams.get() as usize + 1);
+
+    for index in 0..num_streams.get() {
+        let offset = reader
+            .le_u32()
+            .map_err(NameError::read_factory(index, NameErrorKind::NameOffset))?;
+
+        name_offsets.push(offset);
+    }
+    name_offsets.push(name_table_size);
+
+    read_stream_names(reader, &name_offsets, stream_info, diagnostics)
+}
+
+// Names don't always fill the whole name table, so the rest of the table is skipped using its size.
+// Returns the position of the end of the name table.
+fn skip_name_table<R: Read>(
+    reader: &mut Reader<R>,
+    name_table_start: u64,
+    name_table_size: u32,
+) -> Result<u64, HeaderError> {
+    let name_table_end = name_table_start + u64::from(name_table_size);
+
+    if reader.position() < name_table_end {
+        reader
+            .advance_to(name_table_end)
+            .map_err(HeaderError::factory(HeaderErrorKind::NameTable))?;
+    }
+
+    Ok(name_table_end)
+}
+
 fn read_stream_names<R: Read>(
     reader: &mut Reader<R>,
     name_offsets: &[u32],
     stream_info: &mut [StreamInfo],
+    diagnostics: &mut Diagnostics,
 ) -> Result<(), NameError> {
-    for (name_len, index) in name_offsets.windows(2).map(|window| window[1] - window[0]).zip(0..) {
-        stream_info[index as usize].name = reader
+    let limit = diagnostics.limits().name_len;
+
+    for (window, index) in name_offsets.windows(2).zip(0..) {
+        let name_len = window[1]
+            .checked_sub(window[0])
+            .ok_or_else(|| NameError::new(index, NameErrorKind::UnorderedNameOffset))?;
+
+        if name_len > limit {
+            return Err(NameError::new(
+                index,
+                NameErrorKind::TooLong {
+                    len: name_len,
+                    limit,
+                },
+            ));
+        }
+
+        let data = reader
             .take(name_len as usize)
-            .map_err(NameError::read_factory(index, NameErrorKind::Name))?
-            .pipe_as_ref(CStr::from_bytes_until_nul)
-            .map_err(NameError::cstr_factory(index))?
-            .to_str()
-            .map_err(NameError::utf8_factory(index))?
-            .pipe(Some)
-            .map(Into::into);
+            .map_err(NameError::read_factory(index, NameErrorKind::Name))?;
+
+        let name = match CStr::from_bytes_until_nul(&data)
+            .map_err(NameError::cstr_factory(index))
+            .and_then(|name| name.to_str().map_err(NameError::utf8_factory(index)))
+        {
+            Ok(name) => name.into(),
+            Err(e) => {
+                diagnostics.recover(
+                    e,
+                    ParseWarning::LossyName {
+                        stream_index: index,
+                    },
+                )?;
+
+                // names without a null terminator take up the entire space given to them
+                let len = data.iter().position(|byte| *byte == 0).unwrap_or(data.len());
+                String::from_utf8_lossy(&data[..len]).into()
+            }
+        };
+
+        stream_info[index as usize].name = Some(name);
     }
 
     Ok(())
 }
 
 #[cfg(test)]
-mod test {
+pub(crate) mod test {
     use super::error::{ChunkErrorKind::*, HeaderErrorKind::*, StreamErrorKind::*};
-    use super::{Header, RawStreamChunk, RawStreamHeader, StreamHeader, FSB5_MAGIC};
-    use crate::read::Reader;
-    use std::num::{NonZeroU32, NonZeroU8};
+    use super::options::Diagnostics;
+    use super::{
+        AudioFormat, Header, Loop, RawStreamChunk, RawStreamHeader, StreamHeader, StreamInfo,
+        FSB5_MAGIC,
+    };
+    use crate::read::{ReadError, Reader};
+    use std::{
+        num::{NonZeroU32, NonZeroU8},
+        time::Duration,
+    };
+
+    // Builds the information of a 44.1 kHz stream without a loop, metadata, or name.
+    pub(crate) fn test_info(channels: u8, num_samples: u32, size: u32) -> StreamInfo {
+        StreamInfo {
+            sample_rate: NonZeroU32::new(44100).unwrap(),
+            channels: NonZeroU8::new(channels).unwrap(),
+            num_samples: NonZeroU32::new(num_samples).unwrap(),
+            stream_loop: None,
+            _dsp_coeffs: None,
+            vorbis_crc32: None,
+            peak_volume: None,
+            comment: None,
+            size: NonZeroU32::new(size).unwrap(),
+            data_offset: 0,
+            chunks: Box::new([]),
+            name: None,
+        }
+    }
 
     #[test]
     fn read_magic() {
@@ -833,15 +1163,19 @@ mod test {
         let data = 0b011010000101100111100000001011_111001101101001101000100110_11_1110_0;
         let mode = RawStreamHeader::from(data);
         assert!(mode
-            .parse(0)
+            .parse(0, &mut Diagnostics::default())
             .is_err_and(|e| e.kind() == UnknownSampleRate { flag: 0b1110 }));
 
         let data = 0b000000000000000000000000000000_111001101101001101000100110_11_0000_0;
         let mode = RawStreamHeader::from(data);
-        assert!(mode.parse(0).is_err_and(|e| e.kind() == ZeroSamples));
+        assert!(mode
+            .parse(0, &mut Diagnostics::default())
+            .is_err_and(|e| e.kind() == ZeroSamples));
 
         let data = 0b000000000000000000000000000001_000000000000000000000000001_01_1000_0;
-        let mode = RawStreamHeader::from(data).parse(0).unwrap();
+        let mode = RawStreamHeader::from(data)
+            .parse(0, &mut Diagnostics::default())
+            .unwrap();
         assert_eq!(
             mode,
             StreamHeader {
@@ -853,6 +1187,9 @@ mod test {
                 stream_loop: None,
                 dsp_coeffs: None,
                 vorbis_crc32: None,
+                peak_volume: None,
+                comment: None,
+                chunks: Box::new([]),
             }
         );
     }
@@ -882,6 +1219,15 @@ mod test {
         reader = Reader::new(DATA.as_slice());
         assert!(Header::parse(&mut reader).is_err_and(|e| e.is_chunk_err_kind(Flag)));
 
+        reader = Reader::new(DATA.as_slice());
+        let err = Header::parse(&mut reader).unwrap_err();
+        assert_eq!(err.kind(), StreamHeader);
+        assert_eq!(err.stream_kind(), Some(Chunk));
+        assert_eq!(err.chunk_kind(), Some(Flag));
+        assert_eq!(err.stream_index(), Some(0));
+        assert_eq!(err.chunk_index(), Some(0));
+        assert_eq!(err.read_err().map(ReadError::position), Some(DATA.len() as u64));
+
         #[allow(clippy::items_after_statements)]
         fn test_invalid_flag(kind: u8) {
             let flag = u32::from(kind).swap_bytes() << 1;
@@ -904,4 +1250,47 @@ mod test {
             test_invalid_flag(flag);
         }
     }
+
+    #[test]
+    fn parse_loop() {
+        let stream_loop = Loop::parse(0, 10, 19).unwrap();
+        assert_eq!(stream_loop.start(), 10);
+        assert_eq!(stream_loop.end().get(), 20);
+        assert_eq!(stream_loop.len().get(), 10);
+
+        let stream_loop = Loop::parse(0, 0, 0).unwrap();
+        assert_eq!(stream_loop.len().get(), 1);
+
+        assert!(
+            Loop::parse(0, 20, 19).is_err_and(|e| e.kind() == InvalidLoop { start: 20, end: 19 })
+        );
+        assert!(Loop::parse(0, 0, u32::MAX).is_err_and(|e| e.kind()
+            == InvalidLoop {
+                start: 0,
+                end: u32::MAX
+            }));
+    }
+
+    #[test]
+    fn convert_loop_units() {
+        let stream_loop = Loop::parse(0, 22050, 88199).unwrap();
+        let sample_rate = NonZeroU32::new(44100).unwrap();
+        let stereo = NonZeroU8::new(2).unwrap();
+
+        assert!(stream_loop.is_within(NonZeroU32::new(88200).unwrap()));
+        assert!(!stream_loop.is_within(NonZeroU32::new(88199).unwrap()));
+
+        assert_eq!(stream_loop.start_time(sample_rate), Duration::from_millis(500));
+        assert_eq!(stream_loop.end_time(sample_rate), Duration::from_secs(2));
+
+        assert_eq!(
+            stream_loop.byte_range(AudioFormat::Pcm16, stereo),
+            Some(88200..352_800)
+        );
+        assert_eq!(
+            stream_loop.byte_range(AudioFormat::Pcm24, stereo),
+            Some(132_300..529_200)
+        );
+        assert_eq!(stream_loop.byte_range(AudioFormat::Vorbis, stereo), None);
+    }
 }
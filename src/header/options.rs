@@ -0,0 +1,271 @@
+use super::error::{HeaderError, HeaderErrorKind};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// Options for parsing sound banks, used with [`Bank::new_with`].
+///
+/// Options are set by chaining methods on [`ParseOptions::default`]:
+///
+/// ```
+/// use fsbex::ParseOptions;
+///
+/// let options = ParseOptions::default().strict(false);
+/// ```
+///
+/// [`Bank::new_with`]: crate::Bank::new_with
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseOptions {
+    pub(crate) strict: bool,
+    pub(crate) limits: Limits,
+    pub(crate) base_offset: u64,
+}
+
+impl ParseOptions {
+    /// Sets whether parsing fails on data that the crate doesn't recognize.
+    ///
+    /// When disabled, parsing recovers from some problems instead of returning an error,
+    /// which allows reading sound banks created by newer versions of FMOD:
+    /// - stream header chunks of unknown types are kept as raw data (see [`Stream::chunks`])
+    /// - streams with an unknown sample rate flag are given a sample rate of 44100 Hz,
+    ///   unless a stream header chunk specifies the sample rate
+    /// - stream names that are not valid UTF-8 or not null-terminated are converted lossily
+    /// - stream headers that are larger than their declared size are accepted
+    ///
+    /// Every recovered problem is recorded as a [`ParseWarning`], available from [`Bank::warnings`].
+    ///
+    /// This is enabled by default.
+    ///
+    /// [`Bank::warnings`]: crate::Bank::warnings
+    /// [`Stream::chunks`]: crate::Stream::chunks
+    #[must_use]
+    pub fn strict(mut self, enabled: bool) -> Self {
+        self.strict = enabled;
+        self
+    }
+
+    /// Sets the limits on resources used while parsing the sound bank and reading its streams.
+    ///
+    /// See [`Limits`] for more information.
+    #[must_use]
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Sets the byte position where the sound bank starts, for sound banks embedded in a larger file.
+    ///
+    /// Data before this position is skipped when the sound bank is parsed.
+    /// Byte positions in errors and stream data offsets (see [`Stream::data_offset`])
+    /// are still measured from the start of the source, not from the start of the sound bank.
+    ///
+    /// This is 0 by default.
+    ///
+    /// [`Stream::data_offset`]: crate::Stream::data_offset
+    #[must_use]
+    pub fn base_offset(mut self, offset: u64) -> Self {
+        self.base_offset = offset;
+        self
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            strict: true,
+            limits: Limits::default(),
+            base_offset: 0,
+        }
+    }
+}
+
+/// Limits on resources used while parsing a sound bank and reading its streams, used with [`ParseOptions::limits`].
+///
+/// Sizes in a sound bank's headers can't be trusted when it comes from an untrusted source.
+/// When a limit is exceeded, an error is returned instead of allocating memory.
+/// By default, no limits are set.
+///
+/// Limits are set by chaining methods on [`Limits::default`]:
+///
+/// ```
+/// use fsbex::{Limits, ParseOptions};
+///
+/// let limits = Limits::default().max_streams(10_000).max_allocation(256 << 20);
+/// let options = ParseOptions::default().limits(limits);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Limits {
+    pub(crate) streams: u32,
+    pub(crate) stream_size: u32,
+    pub(crate) name_len: u32,
+    pub(crate) allocation: u64,
+}
+
+impl Limits {
+    /// Sets the maximum number of streams in a sound bank.
+    ///
+    /// Exceeding this limit results in [`HeaderErrorKind::TooManyStreams`].
+    #[must_use]
+    pub fn max_streams(mut self, limit: u32) -> Self {
+        self.streams = limit;
+        self
+    }
+
+    /// Sets the maximum size of a stream's data, in bytes.
+    ///
+    /// Exceeding this limit results in [`HeaderErrorKind::StreamTooLarge`].
+    #[must_use]
+    pub fn max_stream_size(mut self, limit: u32) -> Self {
+        self.stream_size = limit;
+        self
+    }
+
+    /// Sets the maximum size of a stream name (including the null terminator), in bytes.
+    ///
+    /// Exceeding this limit results in [`NameErrorKind::TooLong`].
+    ///
+    /// [`NameErrorKind::TooLong`]: crate::NameErrorKind::TooLong
+    #[must_use]
+    pub fn max_name_len(mut self, limit: u32) -> Self {
+        self.name_len = limit;
+        self
+    }
+
+    /// Sets the maximum number of bytes allocated for data read from the sound bank.
+    ///
+    /// This applies separately to:
+    /// - the metadata of all streams (stream headers, chunks and names), which is checked as it is read
+    /// - each stream read into memory by [`Bank::into_iter`] or [`Bank::try_streams`]
+    ///
+    /// Exceeding this limit results in [`HeaderErrorKind::AllocationLimit`] while parsing,
+    /// and [`ReadErrorKind::AllocationLimit`] while reading streams.
+    ///
+    /// [`Bank::into_iter`]: crate::Bank::into_iter
+    /// [`Bank::try_streams`]: crate::Bank::try_streams
+    /// [`ReadErrorKind::AllocationLimit`]: crate::ReadErrorKind::AllocationLimit
+    #[must_use]
+    pub fn max_allocation(mut self, limit: u64) -> Self {
+        self.allocation = limit;
+        self
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            streams: u32::MAX,
+            stream_size: u32::MAX,
+            name_len: u32::MAX,
+            allocation: u64::MAX,
+        }
+    }
+}
+
+/// Represents a problem that was recovered from while parsing a sound bank in non-strict mode.
+///
+/// See [`ParseOptions::strict`] for more information.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ParseWarning {
+    /// A stream header chunk of an unknown type was kept as raw data.
+    UnknownChunk {
+        /// The index of the stream.
+        stream_index: u32,
+        /// The index of the chunk within the stream header.
+        chunk_index: u32,
+        /// The chunk type flag that was not recognized.
+        flag: u8,
+    },
+    /// A stream's sample rate flag was not recognized, so a sample rate of 44100 Hz was assumed.
+    UnknownSampleRate {
+        /// The index of the stream.
+        stream_index: u32,
+        /// The sample rate flag that was not recognized.
+        flag: u8,
+    },
+    /// A stream name was not valid UTF-8 or not null-terminated, so it was converted lossily.
+    LossyName {
+        /// The index of the stream.
+        stream_index: u32,
+    },
+    /// More data was read from the base header and stream headers than their declared size.
+    WrongHeaderSize {
+        /// The declared size of the headers, in bytes.
+        expected: u64,
+        /// The number of bytes that were read.
+        actual: u64,
+    },
+}
+
+impl Display for ParseWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::UnknownChunk {
+                stream_index,
+                chunk_index,
+                flag,
+            } => f.write_fmt(format_args!(
+                "kept chunk with unknown type flag as raw data (0x{flag:02x}) - stream header chunk at index {chunk_index} of stream header at index {stream_index}"
+            )),
+            Self::UnknownSampleRate { stream_index, flag } => f.write_fmt(format_args!(
+                "sample rate flag was not recognized (0x{flag:02x}), assumed 44100 Hz - stream header at index {stream_index}"
+            )),
+            Self::LossyName { stream_index } => f.write_fmt(format_args!(
+                "stream name was converted lossily - stream name at index {stream_index}"
+            )),
+            Self::WrongHeaderSize { expected, actual } => f.write_fmt(format_args!(
+                "total size of base header and stream headers ({actual} bytes) was larger than expected ({expected} bytes)"
+            )),
+        }
+    }
+}
+
+// Tracks how problems are handled while parsing, the warnings for problems that were recovered from,
+// and the amount of memory allocated for metadata.
+#[derive(Debug, Default)]
+pub(crate) struct Diagnostics {
+    lenient: bool,
+    warnings: Vec<ParseWarning>,
+    limits: Limits,
+    allocated: u64,
+}
+
+impl Diagnostics {
+    pub(crate) fn new(options: &ParseOptions) -> Self {
+        Self {
+            lenient: !options.strict,
+            warnings: Vec::new(),
+            limits: options.limits,
+            allocated: 0,
+        }
+    }
+
+    pub(crate) fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    // records memory allocated for metadata, failing if the total exceeds the allocation limit
+    pub(crate) fn allocate(&mut self, bytes: u64) -> Result<(), HeaderError> {
+        self.allocated = self.allocated.saturating_add(bytes);
+
+        if self.allocated > self.limits.allocation {
+            Err(HeaderError::new(HeaderErrorKind::AllocationLimit {
+                limit: self.limits.allocation,
+            }))
+        } else {
+            Ok(())
+        }
+    }
+
+    // In strict mode, the error is returned; otherwise, the warning is recorded and parsing continues.
+    pub(crate) fn recover<E>(&mut self, error: E, warning: ParseWarning) -> Result<(), E> {
+        if self.lenient {
+            self.warnings.push(warning);
+            Ok(())
+        } else {
+            Err(error)
+        }
+    }
+
+    pub(crate) fn into_warnings(self) -> Box<[ParseWarning]> {
+        self.warnings.into_boxed_slice()
+    }
+}
@@ -51,16 +51,44 @@
 //! - PCM (8, 16, 24, 32-bit integer)
 //! - PCM (32-bit float)
 //! - Vorbis
+//!
+//! Streams of any supported format can be written as WAVE files, Ogg Vorbis files, FLAC files or headerless PCM.
+//! See [`EncodeOptions`](encode::EncodeOptions) for more information.
 
+#[cfg(feature = "async")]
+mod async_bank;
 mod bank;
 pub mod encode;
+#[cfg(test)]
+mod fixture;
 mod header;
+#[cfg(feature = "rayon")]
+mod parallel;
 mod read;
+mod salvage;
+mod scan;
 mod stream;
+mod validate;
+
+#[cfg(feature = "async")]
+pub use async_bank::{AsyncBank, AsyncStreamIter};
+pub use bank::{Bank, DecodeError, LazyStreamError, StreamControl, StreamReadError};
+pub use header::{
+    error::{ChunkErrorKind, HeaderErrorKind, NameErrorKind, StreamErrorKind},
+    options::{Limits, ParseOptions, ParseWarning},
+    AudioFormat, BankFlags, BankHeader, Chunk, Loop,
+};
+#[cfg(feature = "rayon")]
+pub use parallel::ExtractError;
+pub use read::{Needed, ReadErrorKind};
+pub use salvage::{Salvage, TruncatedStream};
+pub use scan::{scan, EmbeddedBank};
+pub use stream::{LazyStream, Stream, StreamIntoIter, StreamView, TryStreamIter};
+pub use validate::{ValidationIssue, ValidationReport};
 
-pub use bank::{Bank, DecodeError, LazyStreamError};
-pub use header::{AudioFormat, Loop};
-pub use stream::{LazyStream, Stream, StreamIntoIter};
+// These dependencies are only used by the command-line binary.
+#[cfg(feature = "cli")]
+use {clap as _, glob as _, serde_json as _};
 
 // Decoding and encoding involves casting values from u32 to usize.
 // To ensure correct conversions, only compilation targets where usize is at least 32 bits are allowed.
@@ -0,0 +1,191 @@
+use crate::encode::{encode, EncodeError, EncodeOptions};
+use crate::header::{AudioFormat, BankFlags, StreamInfo};
+use crate::read::{ReadError, ReadErrorKind, Reader};
+use crate::stream::StreamView;
+use rayon::prelude::*;
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    io::{Read, Seek, Write},
+    sync::{Mutex, PoisonError},
+};
+
+// Stream data is read one stream at a time, since the source can't be read from multiple threads at once.
+// Each stream is read by seeking to its data, so streams can be read in any order.
+// Encoding, which takes most of the time for compressed output, is done in parallel.
+pub(crate) fn par_extract<R, F, W>(
+    format: AudioFormat,
+    flags: BankFlags,
+    info: &[StreamInfo],
+    reader: Reader<R>,
+    max_allocation: u64,
+    options: &EncodeOptions,
+    f: F,
+) -> Vec<Result<W, ExtractError>>
+where
+    R: Read + Seek + Send,
+    F: Fn(StreamView<'_>) -> W + Sync,
+    W: Write + Send,
+{
+    let reader = Mutex::new(reader);
+
+    info.par_iter()
+        .enumerate()
+        .map(|(index, info)| {
+            let index = u32::try_from(index).expect("stream count was already validated to be u32");
+
+            let data = {
+                // a panic in another thread doesn't leave the reader in an invalid state
+                let mut reader = reader.lock().unwrap_or_else(PoisonError::into_inner);
+
+                reader
+                    .seek_to(info.data_offset)
+                    .and_then(|()| reader.take_within(info.size.get() as usize, max_allocation))
+                    .map_err(ExtractError::from_read(index))?
+            };
+
+            let sink = f(StreamView::new(index, info));
+            let mut source = Reader::new(data.as_slice());
+
+            encode(format, flags, info, &mut source, sink, options)
+                .map_err(ExtractError::from_encode(index))
+        })
+        .collect()
+}
+
+/// Represents an error that can occur when extracting streams with [`Bank::par_extract`].
+///
+/// [`Bank::par_extract`]: crate::Bank::par_extract
+#[derive(Debug)]
+pub struct ExtractError {
+    index: u32,
+    source: ExtractErrorSource,
+}
+
+#[derive(Debug)]
+enum ExtractErrorSource {
+    Read(ReadError),
+    Encode(EncodeError),
+}
+
+impl ExtractError {
+    fn from_read(index: u32) -> impl FnOnce(ReadError) -> Self {
+        move |source| Self {
+            index,
+            source: ExtractErrorSource::Read(source),
+        }
+    }
+
+    fn from_encode(index: u32) -> impl FnOnce(EncodeError) -> Self {
+        move |source| Self {
+            index,
+            source: ExtractErrorSource::Encode(source),
+        }
+    }
+
+    /// Returns the index of the stream where the error occurred.
+    #[must_use]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the kind of error that occurred while reading the stream data, if reading failed.
+    #[must_use]
+    pub fn read_kind(&self) -> Option<ReadErrorKind> {
+        match &self.source {
+            ExtractErrorSource::Read(e) => Some(e.kind()),
+            ExtractErrorSource::Encode(_) => None,
+        }
+    }
+
+    /// Returns the error that occurred while encoding the stream, if encoding failed.
+    #[must_use]
+    pub fn encode_error(&self) -> Option<&EncodeError> {
+        match &self.source {
+            ExtractErrorSource::Read(_) => None,
+            ExtractErrorSource::Encode(e) => Some(e),
+        }
+    }
+}
+
+impl Display for ExtractError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match &self.source {
+            ExtractErrorSource::Read(_) => {
+                f.write_fmt(format_args!("failed to read stream at index {}", self.index))
+            }
+            ExtractErrorSource::Encode(_) => {
+                f.write_fmt(format_args!("failed to encode stream at index {}", self.index))
+            }
+        }
+    }
+}
+
+impl Error for ExtractError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.source {
+            ExtractErrorSource::Read(e) => Some(e),
+            ExtractErrorSource::Encode(e) => Some(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bank::{test::test_bank, Bank};
+    use crate::read::{Needed, ReadErrorKind};
+    use std::{io::Cursor, num::NonZeroUsize};
+
+    #[test]
+    fn extract_streams_in_order() {
+        let data = test_bank(&[32, 64, 96, 128]);
+
+        let expected: Vec<_> = Bank::new(data.as_slice())
+            .unwrap()
+            .into_iter()
+            .map(|stream| stream.write(Vec::new()).unwrap())
+            .collect();
+
+        let results = Bank::new(Cursor::new(data.as_slice()))
+            .unwrap()
+            .par_extract(|_| Vec::new());
+
+        let extracted: Vec<_> = results.into_iter().map(Result::unwrap).collect();
+        assert_eq!(extracted, expected);
+    }
+
+    #[test]
+    fn extract_streams_with_metadata() {
+        let data = test_bank(&[32, 64, 96]);
+
+        let results = Bank::new(Cursor::new(data.as_slice()))
+            .unwrap()
+            .par_extract(|stream| stream.index().to_le_bytes().to_vec());
+
+        for (result, index) in results.into_iter().zip(0u32..) {
+            assert_eq!(result.unwrap()[..4], index.to_le_bytes());
+        }
+    }
+
+    #[test]
+    fn extract_truncated_bank() {
+        let mut data = test_bank(&[32, 64, 32]);
+        data.truncate(data.len() - 1);
+
+        let results = Bank::new(Cursor::new(data.as_slice()))
+            .unwrap()
+            .par_extract(|_| Vec::new());
+
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_ok());
+
+        let error = results[2].as_ref().unwrap_err();
+        assert_eq!(error.index(), 2);
+        assert_eq!(
+            error.read_kind(),
+            Some(ReadErrorKind::Incomplete(Needed::Size(NonZeroUsize::MIN)))
+        );
+        assert!(error.encode_error().is_none());
+    }
+}
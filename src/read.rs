@@ -6,10 +6,13 @@ use std::{
     num::NonZeroUsize,
 };
 
+// the size of the buffer used for skipping data
+const SCRATCH_SIZE: usize = 8192;
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub(crate) struct Reader<R: Read> {
     inner: R,
-    position: usize,
+    position: u64,
 }
 
 impl<R: Read> Reader<R> {
@@ -20,57 +23,53 @@ impl<R: Read> Reader<R> {
         }
     }
 
-    fn read_to_array<const LEN: usize>(&mut self, buf: &mut [u8; LEN]) -> ReadResult<()> {
-        match self.inner.read(buf) {
-            Ok(n) => {
-                self.position += n;
-
-                if n == LEN {
-                    Ok(())
-                } else {
-                    Err(self.to_error(ReadErrorKind::Incomplete(Needed::Size(
-                        NonZeroUsize::new(LEN - n).expect("n is guaranteed to not equal LEN"),
-                    ))))
-                }
-            }
-            Err(e) => match e.kind() {
-                // this I/O error is non-fatal, so reading is retried
-                ErrorKind::Interrupted => self.read_to_array(buf),
-                ErrorKind::UnexpectedEof => {
-                    Err(self.to_error(ReadErrorKind::Incomplete(Needed::Unknown)))
-                }
-                _ => Err(self.to_error_with_source(ReadErrorKind::Failure, e)),
-            },
+    // used for data that was already read from a sound bank, so that error positions are still absolute
+    pub(crate) fn with_position(reader: R, position: u64) -> Self {
+        Self {
+            inner: reader,
+            position,
         }
     }
 
+    fn read_to_array<const LEN: usize>(&mut self, buf: &mut [u8; LEN]) -> ReadResult<()> {
+        self.read_to_slice(buf)
+    }
+
+    // Short reads are legal (e.g. for pipes, sockets and decompressors),
+    // so reading continues until the buffer is full or the end of the data is reached.
+    // At least one read is always performed, so I/O errors are reported even for empty buffers.
     fn read_to_slice(&mut self, buf: &mut [u8]) -> ReadResult<()> {
-        match self.inner.read(buf) {
-            Ok(n) => {
-                self.position += n;
-                let buf_len = buf.len();
-
-                if n == buf_len {
-                    Ok(())
-                } else {
-                    Err(self.to_error(ReadErrorKind::Incomplete(Needed::Size(
-                        NonZeroUsize::new(buf_len - n)
-                            .expect("n is guaranteed to not equal buf_len"),
-                    ))))
+        let mut filled = 0;
+
+        loop {
+            match self.inner.read(&mut buf[filled..]) {
+                Ok(0) => break,
+                Ok(n) => {
+                    self.position += n as u64;
+                    filled += n;
+
+                    if filled == buf.len() {
+                        break;
+                    }
                 }
+                Err(e) => match e.kind() {
+                    // this I/O error is non-fatal, so reading is retried
+                    ErrorKind::Interrupted => {}
+                    ErrorKind::UnexpectedEof => {
+                        return Err(self.to_error(ReadErrorKind::Incomplete(Needed::Unknown)))
+                    }
+                    _ => return Err(self.to_error_with_source(ReadErrorKind::Failure, e)),
+                },
             }
-            Err(e) => match e.kind() {
-                // this I/O error is non-fatal, so reading is retried
-                ErrorKind::Interrupted => self.read_to_slice(buf),
-                ErrorKind::UnexpectedEof => {
-                    Err(self.to_error(ReadErrorKind::Incomplete(Needed::Unknown)))
-                }
-                _ => Err(self.to_error_with_source(ReadErrorKind::Failure, e)),
-            },
+        }
+
+        match NonZeroUsize::new(buf.len() - filled) {
+            None => Ok(()),
+            Some(missing) => Err(self.to_error(ReadErrorKind::Incomplete(Needed::Size(missing)))),
         }
     }
 
-    pub(crate) fn position(&self) -> usize {
+    pub(crate) fn position(&self) -> u64 {
         self.position
     }
 
@@ -80,23 +79,95 @@ impl<R: Read> Reader<R> {
         Ok(buf)
     }
 
+    // The buffer grows as data is read instead of being allocated up front,
+    // so a length from a corrupt file can't cause a large allocation without data to back it.
     pub(crate) fn take(&mut self, len: usize) -> ReadResult<Vec<u8>> {
-        let mut buf = vec![0; len];
-        Self::read_to_slice(self, &mut buf)?;
+        let mut buf = Vec::new();
+
+        loop {
+            let start = buf.len();
+            buf.resize(start + min(len - start, start.max(SCRATCH_SIZE)), 0);
+
+            self.read_to_slice(&mut buf[start..])
+                .map_err(|e| e.with_missing((len - buf.len()) as u64))?;
+
+            if buf.len() == len {
+                return Ok(buf);
+            }
+        }
+    }
+
+    pub(crate) fn take_within(&mut self, len: usize, limit: u64) -> ReadResult<Vec<u8>> {
+        if len as u64 > limit {
+            return Err(self.to_error(ReadErrorKind::AllocationLimit {
+                requested: len as u64,
+                limit,
+            }));
+        }
+
+        self.take(len)
+    }
+
+    // Like `take_within`, but reaching the end of the data isn't an error: the data read until then is returned.
+    // This is used to recover as much data as possible from truncated sound banks.
+    pub(crate) fn take_available(&mut self, len: usize, limit: u64) -> ReadResult<Vec<u8>> {
+        if len as u64 > limit {
+            return Err(self.to_error(ReadErrorKind::AllocationLimit {
+                requested: len as u64,
+                limit,
+            }));
+        }
+
+        let mut buf = Vec::new();
+
+        while buf.len() < len {
+            let start = buf.len();
+            buf.resize(start + min(len - start, start.max(SCRATCH_SIZE)), 0);
+
+            match self.read_to_slice(&mut buf[start..]) {
+                Ok(()) => {}
+                Err(ReadError {
+                    kind: ReadErrorKind::Incomplete(Needed::Size(missing)),
+                    ..
+                }) => {
+                    buf.truncate(buf.len() - missing.get());
+                    break;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
         Ok(buf)
     }
 
-    pub(crate) fn skip(&mut self, amount: usize) -> ReadResult<()> {
-        let mut buf = vec![0; amount];
-        Self::read_to_slice(self, buf.as_mut_slice())
+    // Skipped data is read into a fixed-size buffer on the stack, so skipping doesn't allocate.
+    pub(crate) fn skip(&mut self, amount: u64) -> ReadResult<()> {
+        let mut scratch = [0; SCRATCH_SIZE];
+        let mut remaining = amount;
+
+        loop {
+            let len = usize::try_from(remaining).map_or(SCRATCH_SIZE, |n| min(n, SCRATCH_SIZE));
+            remaining -= len as u64;
+
+            self.read_to_slice(&mut scratch[..len])
+                .map_err(|e| e.with_missing(remaining))?;
+
+            if remaining == 0 {
+                return Ok(());
+            }
+        }
     }
 
-    pub(crate) fn advance_to(&mut self, position: usize) -> ReadResult<()> {
-        self.skip(position - self.position)
+    // Data can only be read forwards, so advancing to an earlier position is an error.
+    pub(crate) fn advance_to(&mut self, position: u64) -> ReadResult<()> {
+        match position.checked_sub(self.position) {
+            Some(amount) => self.skip(amount),
+            None => Err(self.to_error(ReadErrorKind::Backwards { target: position })),
+        }
     }
 
     // `std::io::Take` isn't used here because constructing it requires taking ownership of the reader
-    pub(crate) fn limit(&mut self, limit: usize) -> CappedReader<'_, R> {
+    pub(crate) fn limit(&mut self, limit: u64) -> CappedReader<'_, R> {
         CappedReader {
             reader: self,
             limit,
@@ -134,40 +205,60 @@ impl<R: Read> Reader<R> {
     }
 }
 
+#[cfg(feature = "rayon")]
+impl<R: Read + std::io::Seek> Reader<R> {
+    // Seeking is relative to the current position, so positions are still measured
+    // from where the reader started even if the underlying source didn't start at its beginning.
+    pub(crate) fn seek_to(&mut self, position: u64) -> ReadResult<()> {
+        let offset = i64::try_from(i128::from(position) - i128::from(self.position))
+            .map_err(|_| IoError::from(ErrorKind::InvalidInput))
+            .and_then(|offset| self.inner.seek(std::io::SeekFrom::Current(offset)));
+
+        match offset {
+            Ok(_) => {
+                self.position = position;
+                Ok(())
+            }
+            Err(e) => Err(self.to_error_with_source(ReadErrorKind::Failure, e)),
+        }
+    }
+}
+
 // essentially `std::io::Take` but with a mutable reference to a reader instead of owning it
 pub(crate) struct CappedReader<'reader, R: Read> {
     reader: &'reader mut Reader<R>,
-    limit: usize,
+    limit: u64,
 }
 
-impl<'reader, R: Read> Read for CappedReader<'reader, R> {
+impl<R: Read> Read for CappedReader<'_, R> {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
         if self.limit == 0 {
             return Ok(0);
         }
 
-        let max = min(buf.len(), self.limit);
+        let max = usize::try_from(self.limit).map_or(buf.len(), |limit| min(buf.len(), limit));
         let n = self.reader.inner.read(&mut buf[..max])?;
-        self.reader.position += n;
-        self.limit -= n;
+        self.reader.position += n as u64;
+        self.limit -= n as u64;
         Ok(n)
     }
 }
 
-impl<'reader, R: BufRead> BufRead for CappedReader<'reader, R> {
+impl<R: BufRead> BufRead for CappedReader<'_, R> {
     fn fill_buf(&mut self) -> Result<&[u8], IoError> {
         if self.limit == 0 {
             return Ok(&[]);
         }
 
         let buf = self.reader.inner.fill_buf()?;
-        let cap = min(buf.len(), self.limit);
+        let cap = usize::try_from(self.limit).map_or(buf.len(), |limit| min(buf.len(), limit));
         Ok(&buf[..cap])
     }
 
     fn consume(&mut self, amt: usize) {
-        let amt = min(amt, self.limit);
-        self.limit -= amt;
+        let amt = usize::try_from(self.limit).map_or(amt, |limit| min(amt, limit));
+        self.limit -= amt as u64;
+        self.reader.position += amt as u64;
         self.reader.inner.consume(amt);
     }
 }
@@ -176,22 +267,41 @@ type ReadResult<T> = Result<T, ReadError>;
 
 #[derive(Debug)]
 pub(crate) struct ReadError {
-    position: usize,
+    position: u64,
     kind: ReadErrorKind,
     source: Option<IoError>,
 }
 
-#[derive(Debug)]
-#[cfg_attr(test, derive(Clone, Copy, PartialEq, Eq))]
-pub(crate) enum ReadErrorKind {
+/// The kind of error that occurred while reading data from a sound bank.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ReadErrorKind {
+    /// The underlying reader returned an I/O error.
     Failure,
+    /// The data ended before all expected bytes were read, e.g. because the file was truncated.
     Incomplete(Needed),
+    /// Reading the data would allocate more memory than the limit set by [`Limits::max_allocation`].
+    ///
+    /// [`Limits::max_allocation`]: crate::Limits::max_allocation
+    AllocationLimit {
+        /// The number of bytes that would be allocated.
+        requested: u64,
+        /// The maximum number of bytes to allocate.
+        limit: u64,
+    },
+    /// Reading needed to move back to an earlier position, which isn't possible because data is only read forwards.
+    Backwards {
+        /// The byte position that reading needed to move back to.
+        target: u64,
+    },
 }
 
-#[derive(Debug)]
-#[cfg_attr(test, derive(Clone, Copy, PartialEq, Eq))]
-pub(crate) enum Needed {
+/// The amount of data that was missing when reading an incomplete sound bank.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Needed {
+    /// The number of bytes that were missing.
     Size(NonZeroUsize),
+    /// The number of missing bytes is unknown.
     Unknown,
 }
 
@@ -213,6 +323,34 @@ impl<R: Read> Reader<R> {
     }
 }
 
+impl ReadError {
+    // used for I/O errors that occur outside of `Reader`, such as when seeking
+    pub(crate) fn io(position: u64, source: IoError) -> Self {
+        Self {
+            position,
+            kind: ReadErrorKind::Failure,
+            source: Some(source),
+        }
+    }
+
+    // Data is sometimes read in parts, in which case an error only knows about the current part.
+    // The size of the parts that weren't read yet is added to the number of missing bytes.
+    fn with_missing(mut self, unread: u64) -> Self {
+        if let ReadErrorKind::Incomplete(Needed::Size(missing)) = &mut self.kind {
+            *missing = missing.saturating_add(usize::try_from(unread).unwrap_or(usize::MAX));
+        }
+        self
+    }
+
+    pub(crate) fn kind(&self) -> ReadErrorKind {
+        self.kind
+    }
+
+    pub(crate) fn position(&self) -> u64 {
+        self.position
+    }
+}
+
 #[cfg(test)]
 impl ReadError {
     fn is_kind(&self, kind: ReadErrorKind) -> bool {
@@ -230,6 +368,12 @@ impl Display for ReadError {
                 }
                 Needed::Unknown => f.write_str("incomplete data"),
             },
+            ReadErrorKind::AllocationLimit { requested, limit } => f.write_fmt(format_args!(
+                "reading data needed more memory ({requested} bytes) than the limit ({limit} bytes)"
+            )),
+            ReadErrorKind::Backwards { target } => {
+                f.write_fmt(format_args!("could not move back to earlier byte position {target}"))
+            }
         }?;
 
         f.write_fmt(format_args!(" - byte position {}", self.position))
@@ -261,7 +405,7 @@ mod test {
         assert_eq!(reader.take_const().unwrap(), [97]);
         assert_eq!(reader.take_const().unwrap(), [98, 99]);
         assert_eq!(reader.take_const().unwrap(), [49, 50, 51]);
-        assert_eq!(reader.take_const().unwrap(), []);
+        assert_eq!(reader.take_const().unwrap(), [0u8; 0]);
         assert!(reader
             .take_const::<1>()
             .is_err_and(|e| e
@@ -283,6 +427,21 @@ mod test {
                 .is_kind(ReadErrorKind::Incomplete(Needed::Size(NonZeroUsize::new(1).unwrap())))));
     }
 
+    #[test]
+    fn skip_large_amounts() {
+        let data = vec![0; 20_000];
+        let mut reader = Reader::new(data.as_slice());
+
+        assert!(reader.skip(17_000).is_ok());
+        assert_eq!(reader.position(), 17_000);
+
+        let err = reader.skip(10_000).unwrap_err();
+        assert!(err.is_kind(ReadErrorKind::Incomplete(Needed::Size(
+            NonZeroUsize::new(7000).unwrap()
+        ))));
+        assert_eq!(err.position(), 20_000);
+    }
+
     #[test]
     fn advance_to_position() {
         let data = b"abc123";
@@ -303,6 +462,53 @@ mod test {
                 .is_kind(ReadErrorKind::Incomplete(Needed::Size(NonZeroUsize::new(4).unwrap())))));
     }
 
+    #[test]
+    fn advance_to_earlier_position() {
+        let data = b"abc123";
+        let mut reader = Reader::new(data.as_slice());
+
+        assert!(reader.advance_to(4).is_ok());
+
+        let err = reader.advance_to(2).unwrap_err();
+        assert!(err.is_kind(ReadErrorKind::Backwards { target: 2 }));
+        assert_eq!(err.position(), 4);
+        assert_eq!(reader.position(), 4);
+    }
+
+    #[test]
+    fn track_positions_past_4_gib() {
+        let mut reader = Reader::with_position(b"abcd".as_slice(), u64::from(u32::MAX));
+
+        assert!(reader.skip(2).is_ok());
+        assert_eq!(reader.position(), 0x1_0000_0001);
+        assert!(reader.advance_to(0x1_0000_0002).is_ok());
+
+        let err = reader.le_u32().unwrap_err();
+        assert_eq!(err.position(), 0x1_0000_0003);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn seek_to_position() {
+        use std::io::Cursor;
+
+        let data = b"abc123";
+        let mut reader = Reader::new(Cursor::new(data.as_slice()));
+
+        assert!(reader.seek_to(3).is_ok());
+        assert_eq!(reader.u8().unwrap(), b'1');
+        assert!(reader.seek_to(1).is_ok());
+        assert_eq!(reader.position(), 1);
+        assert_eq!(reader.u8().unwrap(), b'b');
+
+        // the underlying source doesn't have to start at its beginning
+        let mut source = Cursor::new(data.as_slice());
+        source.set_position(2);
+        let mut reader = Reader::new(source);
+        assert!(reader.seek_to(3).is_ok());
+        assert_eq!(reader.u8().unwrap(), b'3');
+    }
+
     #[test]
     fn parse_single_number() {
         let data = b"\x00\x00\x00\x00\x00\x00";
@@ -440,6 +646,50 @@ mod test {
         assert!(reader.unit().is_err_and(|e| e.is_kind(ReadErrorKind::Failure)));
     }
 
+    // returns at most one byte per read, like a slow pipe or socket
+    struct ShortReader<'data>(&'data [u8]);
+
+    impl Read for ShortReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+            let len = buf.len().min(self.0.len()).min(1);
+            buf[..len].copy_from_slice(&self.0[..len]);
+            self.0 = &self.0[len..];
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn handle_short_reads() {
+        let data = b"\x11\x00\x00\x00\x01\x00\x00\x00\x00\x00\x00\x00\xFF\xFF";
+        let mut reader = Reader::new(ShortReader(data));
+
+        assert_eq!(reader.le_u32().unwrap(), 17);
+        assert_eq!(reader.le_u64().unwrap(), 1);
+        assert_eq!(reader.take(1).unwrap(), [0xFF]);
+        assert_eq!(reader.position(), 13);
+
+        let err = reader.le_u32().unwrap_err();
+        assert!(err.is_kind(ReadErrorKind::Incomplete(Needed::Size(NonZeroUsize::new(3).unwrap()))));
+        assert_eq!(err.position(), 14);
+    }
+
+    #[test]
+    fn take_available_bytes() {
+        let data = vec![7; 10_000];
+        let mut reader = Reader::new(data.as_slice());
+
+        assert_eq!(reader.take_available(4, u64::MAX).unwrap(), [7; 4]);
+        assert_eq!(reader.take_available(20_000, u64::MAX).unwrap().len(), 9996);
+        assert_eq!(reader.position(), 10_000);
+        assert!(reader.take_available(1, u64::MAX).unwrap().is_empty());
+        assert!(reader.take_available(2, 1).is_err_and(|e| e.is_kind(
+            ReadErrorKind::AllocationLimit {
+                requested: 2,
+                limit: 1
+            }
+        )));
+    }
+
     #[test]
     fn capped_reader_works() {
         let data = b"abcd1234";
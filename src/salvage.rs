@@ -0,0 +1,318 @@
+use crate::bank::StreamReadError;
+use crate::encode::decodable_prefix;
+use crate::header::{AudioFormat, BankFlags, StreamInfo};
+use crate::read::Reader;
+use crate::stream::Stream;
+use std::{io::Read, num::NonZeroU32};
+
+/// The streams recovered from a sound bank whose stream data was cut short, returned from [`Bank::salvage`].
+///
+/// Streams are read in order until the data ends. Every stream whose data is complete is recovered as is.
+/// The stream where the data ends is truncated to the audio that can still be decoded
+/// (whole frames of PCM samples or complete Vorbis packets), and all following streams are missing.
+///
+/// [`Bank::salvage`]: crate::Bank::salvage
+#[derive(Debug)]
+pub struct Salvage {
+    streams: Box<[Stream]>,
+    complete: u32,
+    truncated: Option<TruncatedStream>,
+    missing: u32,
+    error: Option<StreamReadError>,
+}
+
+impl Salvage {
+    /// Returns the recovered streams, in order.
+    ///
+    /// This includes the truncated stream if any of its audio could be recovered (see [`TruncatedStream::is_recovered`]).
+    /// Its size and sample count describe the recovered audio instead of the values in the stream header.
+    #[must_use]
+    pub fn streams(&self) -> &[Stream] {
+        &self.streams
+    }
+
+    /// Converts this [`Salvage`] into the recovered streams.
+    ///
+    /// See [`Salvage::streams`] for more information.
+    #[must_use]
+    pub fn into_streams(self) -> Box<[Stream]> {
+        self.streams
+    }
+
+    /// Returns the number of streams whose data was complete.
+    #[must_use]
+    pub fn complete_streams(&self) -> u32 {
+        self.complete
+    }
+
+    /// Returns information about the stream where the data ended, if the data ended within a stream.
+    #[must_use]
+    pub fn truncated(&self) -> Option<TruncatedStream> {
+        self.truncated
+    }
+
+    /// Returns the number of streams after the truncated stream, which have no data at all.
+    #[must_use]
+    pub fn missing_streams(&self) -> u32 {
+        self.missing
+    }
+
+    /// Returns the error that stopped reading, if reading failed for a reason other than the data ending
+    /// (e.g. an I/O error or exceeding [`Limits::max_allocation`]).
+    ///
+    /// The stream where reading failed and all following streams are counted as missing.
+    ///
+    /// [`Limits::max_allocation`]: crate::Limits::max_allocation
+    #[must_use]
+    pub fn error(&self) -> Option<&StreamReadError> {
+        self.error.as_ref()
+    }
+
+    /// Returns `true` if every stream in the sound bank was complete.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.truncated.is_none() && self.missing == 0
+    }
+}
+
+/// Information about the stream where the data of a truncated sound bank ends.
+///
+/// See [`Salvage`] for more information.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TruncatedStream {
+    index: u32,
+    sample_count: NonZeroU32,
+    recovered_samples: u32,
+    recovered_size: u32,
+}
+
+impl TruncatedStream {
+    /// Returns the index of the stream within the sound bank.
+    #[must_use]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the number of samples in the stream given by its stream header.
+    #[must_use]
+    pub fn sample_count(&self) -> NonZeroU32 {
+        self.sample_count
+    }
+
+    /// Returns the number of samples that could be recovered.
+    ///
+    /// This is 0 if no audio could be decoded, including when the audio format can't be decoded by this crate.
+    #[must_use]
+    pub fn recovered_samples(&self) -> u32 {
+        self.recovered_samples
+    }
+
+    /// Returns the size of the recovered stream data, in bytes.
+    #[must_use]
+    pub fn recovered_size(&self) -> u32 {
+        self.recovered_size
+    }
+
+    /// Returns `true` if any audio could be recovered, in which case the stream is included in [`Salvage::streams`].
+    #[must_use]
+    pub fn is_recovered(&self) -> bool {
+        self.recovered_samples != 0
+    }
+}
+
+pub(crate) fn salvage<R: Read>(
+    format: AudioFormat,
+    flags: BankFlags,
+    info: Box<[StreamInfo]>,
+    reader: &mut Reader<R>,
+    max_allocation: u64,
+) -> Salvage {
+    let num_streams =
+        u32::try_from(info.len()).expect("stream count was already validated to be u32");
+    let mut streams = Vec::with_capacity(info.len());
+    let mut complete = 0;
+    let mut truncated = None;
+    let mut error = None;
+
+    for (info, index) in info.into_vec().into_iter().zip(0..) {
+        let size = info.size.get() as usize;
+
+        let data = match reader.take_available(size, max_allocation) {
+            Ok(data) => data,
+            Err(e) => {
+                error = Some(StreamReadError::factory(index)(e));
+                break;
+            }
+        };
+
+        if data.len() == size {
+            streams.push(Stream::new(format, flags, info, data.into_boxed_slice()));
+            complete += 1;
+            continue;
+        }
+
+        let (stream, report) = salvage_truncated(format, flags, info, data, index);
+        streams.extend(stream);
+        truncated = Some(report);
+        break;
+    }
+
+    let missing = num_streams - complete - u32::from(truncated.is_some());
+
+    Salvage {
+        streams: streams.into_boxed_slice(),
+        complete,
+        truncated,
+        missing,
+        error,
+    }
+}
+
+// The stream data is cut to the audio that can be decoded,
+// and the stream information is changed to match the recovered audio.
+fn salvage_truncated(
+    format: AudioFormat,
+    flags: BankFlags,
+    mut info: StreamInfo,
+    mut data: Vec<u8>,
+    index: u32,
+) -> (Option<Stream>, TruncatedStream) {
+    let mut report = TruncatedStream {
+        index,
+        sample_count: info.num_samples,
+        recovered_samples: 0,
+        recovered_size: 0,
+    };
+
+    let Some((size, samples)) = decodable_prefix(format, &info, &data) else {
+        return (None, report);
+    };
+
+    let (Some(num_samples), Some(recovered_size)) = (
+        NonZeroU32::new(samples),
+        u32::try_from(size).ok().and_then(NonZeroU32::new),
+    ) else {
+        return (None, report);
+    };
+
+    data.truncate(size);
+    info.size = recovered_size;
+    info.num_samples = num_samples;
+    info.stream_loop = info
+        .stream_loop
+        .filter(|stream_loop| stream_loop.is_within(num_samples));
+
+    report.recovered_samples = samples;
+    report.recovered_size = recovered_size.get();
+
+    let stream = Stream::new(format, flags, info, data.into_boxed_slice());
+    (Some(stream), report)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bank::{test::test_bank, Bank};
+    use crate::encode::VORBIS_LOOKUP;
+
+    #[test]
+    fn salvage_truncated_bank() {
+        // the second stream is cut short by 9 bytes, and the third stream is missing
+        let mut data = test_bank(&[32, 64, 32]);
+        data.truncate(data.len() - 41);
+
+        let salvage = Bank::new(data.as_slice()).unwrap().salvage();
+        assert!(!salvage.is_complete());
+        assert_eq!(salvage.complete_streams(), 1);
+        assert_eq!(salvage.missing_streams(), 1);
+        assert!(salvage.error().is_none());
+
+        // 55 bytes are left, which contain 27 whole 16-bit samples
+        let truncated = salvage.truncated().unwrap();
+        assert_eq!(truncated.index(), 1);
+        assert_eq!(truncated.sample_count().get(), 32);
+        assert_eq!(truncated.recovered_samples(), 27);
+        assert_eq!(truncated.recovered_size(), 54);
+        assert!(truncated.is_recovered());
+
+        let streams = salvage.into_streams();
+        assert_eq!(streams.len(), 2);
+        assert_eq!(streams[1].sample_count().get(), 27);
+        assert_eq!(streams[1].size().get(), 54);
+
+        let wav = streams.into_vec().pop().unwrap().write(Vec::new()).unwrap();
+        assert_eq!(wav.len(), 44 + 54);
+    }
+
+    #[test]
+    fn salvage_complete_bank() {
+        let data = test_bank(&[32, 64]);
+
+        let salvage = Bank::new(data.as_slice()).unwrap().salvage();
+        assert!(salvage.is_complete());
+        assert_eq!(salvage.complete_streams(), 2);
+        assert_eq!(salvage.truncated(), None);
+        assert_eq!(salvage.streams().len(), 2);
+    }
+
+    #[test]
+    fn salvage_without_recoverable_audio() {
+        // only 1 byte of the second stream is left, which isn't a whole sample
+        let mut data = test_bank(&[32, 64]);
+        data.truncate(data.len() - 63);
+
+        let salvage = Bank::new(data.as_slice()).unwrap().salvage();
+        assert_eq!(salvage.complete_streams(), 1);
+        assert_eq!(salvage.missing_streams(), 0);
+        assert_eq!(salvage.streams().len(), 1);
+
+        let truncated = salvage.truncated().unwrap();
+        assert_eq!(truncated.recovered_samples(), 0);
+        assert!(!truncated.is_recovered());
+    }
+
+    #[test]
+    fn salvage_truncated_vorbis_stream() {
+        let crc32 = *VORBIS_LOOKUP.keys().next().unwrap();
+
+        let mut data = Vec::from(*b"FSB5");
+        for field in [1, 1, 16, 0, 8 * 6, 15] {
+            data.extend_from_slice(&u32::to_le_bytes(field));
+        }
+        data.resize(60, 0);
+
+        // has chunks, sample rate flag 8 (44100 Hz), 1 channel, 896 samples
+        let stream_header: u64 = 1 | 8 << 1 | 896 << 34;
+        data.extend_from_slice(&stream_header.to_le_bytes());
+        let seek_table_chunk: u32 = 4 << 1 | 11 << 25;
+        data.extend_from_slice(&seek_table_chunk.to_le_bytes());
+        data.extend_from_slice(&crc32.to_le_bytes());
+
+        // 8 packets that mark the channel as unused, so they decode to silence with any setup header:
+        // the first packet only starts the first window, and every following (short) block adds 128 samples
+        for _ in 0..8 {
+            data.extend_from_slice(&4u16.to_le_bytes());
+            data.extend_from_slice(&[0; 4]);
+        }
+
+        let stream = Bank::new(data.as_slice()).unwrap().into_iter().next().unwrap();
+        assert!(stream.write(Vec::new()).is_ok());
+
+        // the last packet is cut short by 2 bytes, so only the 7 packets before it are recovered
+        data.truncate(data.len() - 2);
+
+        let salvage = Bank::new(data.as_slice()).unwrap().salvage();
+        assert_eq!(salvage.complete_streams(), 0);
+        assert_eq!(salvage.missing_streams(), 0);
+
+        let truncated = salvage.truncated().unwrap();
+        assert_eq!(truncated.index(), 0);
+        assert_eq!(truncated.sample_count().get(), 896);
+        assert_eq!(truncated.recovered_samples(), 768);
+        assert_eq!(truncated.recovered_size(), 7 * 6);
+
+        let stream = salvage.into_streams().into_vec().pop().unwrap();
+        assert_eq!(stream.sample_count().get(), 768);
+        assert_eq!(stream.size().get(), 7 * 6);
+        assert!(stream.write(Vec::new()).unwrap().starts_with(b"OggS"));
+    }
+}
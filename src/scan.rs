@@ -0,0 +1,238 @@
+use crate::bank::{Bank, DecodeError};
+use crate::header::{
+    options::{Diagnostics, Limits, ParseOptions},
+    AudioFormat, Header, FSB5_MAGIC,
+};
+use crate::read::Reader;
+use std::{
+    io::{Error as IoError, Read, Seek, SeekFrom, Take},
+    num::NonZeroU32,
+    ops::Range,
+};
+
+// the number of bytes searched for a file signature at a time
+const SCAN_BUFFER_SIZE: usize = 64 * 1024;
+
+/// A sound bank found inside a larger source by [`scan`].
+///
+/// The sound bank's file header was parsed successfully and all of its data lies within the source,
+/// so it can be opened with [`EmbeddedBank::open`] or [`Bank::new_at`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EmbeddedBank {
+    offset: u64,
+    size: u64,
+    format: AudioFormat,
+    num_streams: NonZeroU32,
+}
+
+impl EmbeddedBank {
+    /// Returns the byte position in the source where the sound bank starts.
+    #[must_use]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the size of the sound bank (headers, name table and stream data), in bytes.
+    #[must_use]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns the range of byte positions in the source taken up by the sound bank.
+    #[must_use]
+    pub fn range(&self) -> Range<u64> {
+        self.offset..self.offset + self.size
+    }
+
+    /// Returns the audio format of streams in the sound bank.
+    #[must_use]
+    pub fn format(&self) -> AudioFormat {
+        self.format
+    }
+
+    /// Returns the number of streams in the sound bank.
+    #[must_use]
+    pub fn num_streams(&self) -> NonZeroU32 {
+        self.num_streams
+    }
+
+    /// Opens the sound bank from the source it was found in.
+    ///
+    /// This is equivalent to calling [`Bank::new_at`] with the offset and size of the sound bank.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if seeking to the sound bank or parsing of its file header failed.
+    /// See [`DecodeError`] for more information.
+    pub fn open<R: Read + Seek>(&self, source: R) -> Result<Bank<Take<R>>, DecodeError> {
+        Bank::new_at(source, self.offset, self.size)
+    }
+}
+
+/// Searches a source, such as a game archive, for embedded sound banks.
+///
+/// Every occurrence of a file signature (only `FSB5` is supported) is checked by parsing a file header there.
+/// Occurrences where parsing fails, or where the sound bank would extend past the end of the source, are ignored.
+/// Searching continues after the end of each sound bank that is found,
+/// so signatures within stream data are not mistaken for sound banks.
+///
+/// Sound banks are returned in the order they appear in the source.
+///
+/// # Examples
+///
+/// ```no_run
+/// use fsbex::scan;
+/// use std::{error::Error, fs::File, io::BufReader};
+///
+/// fn list_embedded_banks() -> Result<(), Box<dyn Error>> {
+///     let mut file = BufReader::new(File::open("archive.pak")?);
+///
+///     for found in scan(&mut file)? {
+///         let bank = found.open(&mut file)?;
+///         println!("{} streams at byte {}", bank.num_streams(), found.offset());
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+///
+/// # Errors
+///
+/// This function returns an error if the underlying reader failed to read or seek.
+/// Errors while parsing the file header at an occurrence of a signature are not reported.
+pub fn scan<R: Read + Seek>(mut source: R) -> Result<Vec<EmbeddedBank>, IoError> {
+    let source_len = source.seek(SeekFrom::End(0))?;
+    let mut banks = Vec::new();
+    let mut buf = Vec::with_capacity(SCAN_BUFFER_SIZE);
+    let mut position = 0;
+
+    while position < source_len {
+        let _ = source.seek(SeekFrom::Start(position))?;
+
+        buf.clear();
+        let _ = (&mut source).take(SCAN_BUFFER_SIZE as u64).read_to_end(&mut buf)?;
+
+        match buf.windows(FSB5_MAGIC.len()).position(|window| window == FSB5_MAGIC) {
+            Some(index) => {
+                let offset = position + index as u64;
+
+                position = match parse_at(&mut source, offset, source_len)? {
+                    Some(bank) => {
+                        banks.push(bank);
+                        bank.range().end
+                    }
+                    None => offset + 1,
+                };
+            }
+            // The signature could be split between this buffer and the next one,
+            // so the end of this buffer is searched again.
+            None if buf.len() >= FSB5_MAGIC.len() => {
+                position += (buf.len() - (FSB5_MAGIC.len() - 1)) as u64;
+            }
+            None => break,
+        }
+    }
+
+    Ok(banks)
+}
+
+fn parse_at<R: Read + Seek>(
+    source: &mut R,
+    offset: u64,
+    source_len: u64,
+) -> Result<Option<EmbeddedBank>, IoError> {
+    let _ = source.seek(SeekFrom::Start(offset))?;
+
+    // Each stream header is at least 8 bytes long, so a sound bank can't have more streams than this.
+    // This stops random data from claiming a huge number of streams.
+    let max_streams = u32::try_from((source_len - offset) / 8).unwrap_or(u32::MAX);
+    let options = ParseOptions::default().limits(Limits::default().max_streams(max_streams));
+
+    let mut reader = Reader::new(&mut *source);
+
+    let Ok(header) = Header::parse_with(&mut reader, Diagnostics::new(&options)) else {
+        return Ok(None);
+    };
+
+    let size = header.bank_size();
+
+    if offset + size > source_len {
+        return Ok(None);
+    }
+
+    Ok(Some(EmbeddedBank {
+        offset,
+        size,
+        format: header.format,
+        num_streams: header.num_streams(),
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::scan;
+    use crate::bank::test::test_bank;
+    use crate::header::AudioFormat;
+    use std::io::Cursor;
+
+    #[test]
+    fn find_embedded_banks() {
+        let first = test_bank(&[32, 64]);
+        let second = test_bank(&[96]);
+
+        // a signature without a valid header, then two banks, then a truncated bank
+        let mut data = b"junkFSB5junk".to_vec();
+        data.extend(&first);
+        data.extend([0; 7]);
+        data.extend(&second);
+        data.extend(&first[..first.len() - 1]);
+
+        let found = scan(Cursor::new(data.as_slice())).unwrap();
+        assert_eq!(found.len(), 2);
+
+        assert_eq!(found[0].offset(), 12);
+        assert_eq!(found[0].size(), first.len() as u64);
+        assert_eq!(found[0].format(), AudioFormat::Pcm16);
+        assert_eq!(found[0].num_streams().get(), 2);
+
+        let second_offset = 12 + first.len() as u64 + 7;
+        assert_eq!(found[1].range(), second_offset..second_offset + second.len() as u64);
+        assert_eq!(found[1].num_streams().get(), 1);
+
+        let streams: Vec<_> = found[1]
+            .open(Cursor::new(data.as_slice()))
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(streams.len(), 1);
+        assert_eq!(streams[0].size().get(), 96);
+    }
+
+    #[test]
+    fn skip_banks_with_invalid_names() {
+        let bank = test_bank(&[32]);
+
+        // the offset of the only name is past the end of the name table
+        let mut invalid = bank.clone();
+        invalid[16..20].copy_from_slice(&8u32.to_le_bytes());
+        drop(invalid.splice(68..68, [100u32, 0].into_iter().flat_map(u32::to_le_bytes)));
+
+        let mut data = invalid;
+        data.extend(&bank);
+
+        let found = scan(Cursor::new(data.as_slice())).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].offset(), data.len() as u64 - bank.len() as u64);
+    }
+
+    #[test]
+    fn find_banks_across_buffer_boundaries() {
+        // the signature starts 2 bytes before the end of the first buffer
+        let mut data = vec![0; super::SCAN_BUFFER_SIZE - 2];
+        data.extend(test_bank(&[32]));
+
+        let found = scan(Cursor::new(data.as_slice())).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].offset(), super::SCAN_BUFFER_SIZE as u64 - 2);
+    }
+}
@@ -1,9 +1,15 @@
-use crate::encode::{encode, EncodeError};
-use crate::header::{AudioFormat, Loop, StreamInfo};
-use crate::read::Reader;
+use crate::bank::StreamReadError;
+use crate::encode::{encode, EncodeError, EncodeOptions};
+use crate::header::{
+    samples_to_duration, AudioFormat, BankFlags, Chunk, Loop, RawChunk, StreamInfo,
+};
+use crate::read::{ReadError, Reader};
 use std::{
     io::{Read, Write},
+    iter::FusedIterator,
     num::{NonZeroU32, NonZeroU8},
+    ops::Range,
+    time::Duration,
 };
 
 /// An audio stream of data that has not been read yet.
@@ -18,7 +24,7 @@ use std::{
 pub struct LazyStream<'bank, R: Read> {
     index: u32,
     format: AudioFormat,
-    flags: u32,
+    flags: BankFlags,
     info: &'bank StreamInfo,
     reader: &'bank mut Reader<R>,
 }
@@ -27,7 +33,7 @@ impl<'bank, R: Read> LazyStream<'bank, R> {
     pub(crate) fn new(
         index: u32,
         format: AudioFormat,
-        flags: u32,
+        flags: BankFlags,
         info: &'bank StreamInfo,
         reader: &'bank mut Reader<R>,
     ) -> Self {
@@ -93,13 +99,66 @@ impl<'bank, R: Read> LazyStream<'bank, R> {
         }
     }
 
+    /// Returns the position of the stream data within the sound bank file, in bytes.
+    ///
+    /// This is the absolute position from the start of the file, not from the start of stream data.
+    #[must_use]
+    pub fn data_offset(&self) -> u64 {
+        self.info.data_offset
+    }
+
+    /// Returns the range of bytes occupied by the stream data within the sound bank file.
+    ///
+    /// See [`LazyStream::data_offset`] for more information.
+    #[must_use]
+    pub fn data_range(&self) -> Range<u64> {
+        self.info.data_range()
+    }
+
+    /// Returns the playback duration of the stream, computed from its sample count and sample rate.
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        samples_to_duration(self.info.num_samples.get().into(), self.info.sample_rate)
+    }
+
+    /// Returns the peak volume of the stream, if it was computed when the sound bank was built.
+    #[must_use]
+    pub fn peak_volume(&self) -> Option<f32> {
+        self.info.peak_volume.map(f32::from_bits)
+    }
+
+    /// Returns the comment attached to the stream, if it exists.
+    #[must_use]
+    pub fn comment(&self) -> Option<&str> {
+        self.info.comment.as_deref()
+    }
+
+    /// Returns an iterator over all chunks in the stream header, in the order they are stored.
+    ///
+    /// This includes chunks whose information is already available through other methods
+    /// (e.g. [`LazyStream::loop_info`]), as well as chunks of unknown types.
+    pub fn chunks(&self) -> impl Iterator<Item = Chunk<'_>> + '_ {
+        self.info.chunks.iter().map(RawChunk::to_chunk)
+    }
+
     /// Encodes the stream data by writing audio samples to a writer.
     ///
+    /// This is equivalent to calling [`LazyStream::write_with`] with [`EncodeOptions::default`].
+    ///
     /// # Errors
     /// This function returns an error if the stream data could not be successfully written.
     /// See [`EncodeError`] for more information.
     pub fn write<W: Write>(self, sink: W) -> Result<W, EncodeError> {
-        encode(self.format, self.flags, self.info, self.reader, sink)
+        self.write_with(sink, &EncodeOptions::default())
+    }
+
+    /// Encodes the stream data by writing audio samples to a writer, using the given [`EncodeOptions`].
+    ///
+    /// # Errors
+    /// This function returns an error if the stream data could not be successfully written.
+    /// See [`EncodeError`] for more information.
+    pub fn write_with<W: Write>(self, sink: W, options: &EncodeOptions) -> Result<W, EncodeError> {
+        encode(self.format, self.flags, self.info, self.reader, sink, options)
     }
 }
 
@@ -115,13 +174,18 @@ impl<'bank, R: Read> LazyStream<'bank, R> {
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Stream {
     format: AudioFormat,
-    flags: u32,
+    flags: BankFlags,
     info: StreamInfo,
     data: Box<[u8]>,
 }
 
 impl Stream {
-    pub(crate) fn new(format: AudioFormat, flags: u32, info: StreamInfo, data: Box<[u8]>) -> Self {
+    pub(crate) fn new(
+        format: AudioFormat,
+        flags: BankFlags,
+        info: StreamInfo,
+        data: Box<[u8]>,
+    ) -> Self {
         Self {
             format,
             flags,
@@ -177,14 +241,148 @@ impl Stream {
         }
     }
 
+    /// Returns the position of the stream data within the sound bank file, in bytes.
+    ///
+    /// This is the absolute position from the start of the file, not from the start of stream data.
+    #[must_use]
+    pub fn data_offset(&self) -> u64 {
+        self.info.data_offset
+    }
+
+    /// Returns the range of bytes occupied by the stream data within the sound bank file.
+    ///
+    /// See [`Stream::data_offset`] for more information.
+    #[must_use]
+    pub fn data_range(&self) -> Range<u64> {
+        self.info.data_range()
+    }
+
+    /// Returns the playback duration of the stream, computed from its sample count and sample rate.
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        samples_to_duration(self.info.num_samples.get().into(), self.info.sample_rate)
+    }
+
+    /// Returns the peak volume of the stream, if it was computed when the sound bank was built.
+    #[must_use]
+    pub fn peak_volume(&self) -> Option<f32> {
+        self.info.peak_volume.map(f32::from_bits)
+    }
+
+    /// Returns the comment attached to the stream, if it exists.
+    #[must_use]
+    pub fn comment(&self) -> Option<&str> {
+        self.info.comment.as_deref()
+    }
+
+    /// Returns an iterator over all chunks in the stream header, in the order they are stored.
+    ///
+    /// This includes chunks whose information is already available through other methods
+    /// (e.g. [`Stream::loop_info`]), as well as chunks of unknown types.
+    pub fn chunks(&self) -> impl Iterator<Item = Chunk<'_>> + '_ {
+        self.info.chunks.iter().map(RawChunk::to_chunk)
+    }
+
     /// Encodes the stream data by writing audio samples to a writer.
     ///
+    /// This is equivalent to calling [`Stream::write_with`] with [`EncodeOptions::default`].
+    ///
     /// # Errors
     /// This function returns an error if the stream data could not be successfully written.
     /// See [`EncodeError`] for more information.
     pub fn write<W: Write>(self, sink: W) -> Result<W, EncodeError> {
+        self.write_with(sink, &EncodeOptions::default())
+    }
+
+    /// Encodes the stream data by writing audio samples to a writer, using the given [`EncodeOptions`].
+    ///
+    /// # Errors
+    /// This function returns an error if the stream data could not be successfully written.
+    /// See [`EncodeError`] for more information.
+    pub fn write_with<W: Write>(self, sink: W, options: &EncodeOptions) -> Result<W, EncodeError> {
         let mut reader = Reader::new(&*self.data);
-        encode(self.format, self.flags, &self.info, &mut reader, sink)
+        encode(self.format, self.flags, &self.info, &mut reader, sink, options)
+    }
+}
+
+/// Metadata of a stream, borrowed from a sound bank without reading any stream data.
+///
+/// [`StreamView`] is accessible through the [`Bank::stream_infos`] method.
+///
+/// [`Bank::stream_infos`]: crate::Bank::stream_infos
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StreamView<'bank> {
+    index: u32,
+    info: &'bank StreamInfo,
+}
+
+impl<'bank> StreamView<'bank> {
+    pub(crate) fn new(index: u32, info: &'bank StreamInfo) -> Self {
+        Self { index, info }
+    }
+
+    /// Returns the index of the stream within the sound bank.
+    #[must_use]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the name of the stream, if it exists.
+    #[must_use]
+    pub fn name(&self) -> Option<&'bank str> {
+        self.info.name.as_deref()
+    }
+
+    /// Returns the sample rate of the stream (in Hz).
+    #[must_use]
+    pub fn sample_rate(&self) -> NonZeroU32 {
+        self.info.sample_rate
+    }
+
+    /// Returns the number of channels in the stream.
+    #[must_use]
+    pub fn channels(&self) -> NonZeroU8 {
+        self.info.channels
+    }
+
+    /// Returns the number of samples (per channel) in the stream.
+    #[must_use]
+    pub fn sample_count(&self) -> NonZeroU32 {
+        self.info.num_samples
+    }
+
+    /// Returns loop information, if it exists.
+    #[must_use]
+    pub fn loop_info(&self) -> Option<Loop> {
+        self.info.stream_loop
+    }
+
+    /// Returns the size of the stream data, in bytes.
+    #[must_use]
+    pub fn size(&self) -> NonZeroU32 {
+        self.info.size
+    }
+
+    /// Returns the position of the stream data within the sound bank file, in bytes.
+    ///
+    /// This is the absolute position from the start of the file, not from the start of stream data.
+    #[must_use]
+    pub fn data_offset(&self) -> u64 {
+        self.info.data_offset
+    }
+
+    /// Returns the range of bytes occupied by the stream data within the sound bank file.
+    ///
+    /// See [`StreamView::data_offset`] for more information.
+    #[must_use]
+    pub fn data_range(&self) -> Range<u64> {
+        self.info.data_range()
+    }
+
+    /// Returns the playback duration of the stream, computed from its sample count and sample rate.
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        samples_to_duration(self.info.num_samples.get().into(), self.info.sample_rate)
     }
 }
 
@@ -192,65 +390,125 @@ impl Stream {
 ///
 /// This type is returned from [`Bank::into_iter`].
 /// When iterating, `Some(Stream)` is returned if a stream was successfully read from the sound bank, and `None` otherwise.
+/// After a stream fails to be read, no more streams are returned.
+/// Use [`Bank::try_streams`] to find out why reading failed.
 ///
 /// [`Bank::into_iter`]: crate::Bank::into_iter
+/// [`Bank::try_streams`]: crate::Bank::try_streams
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct StreamIntoIter<R: Read> {
+    inner: TryStreamIter<R>,
+}
+
+impl<R: Read> From<TryStreamIter<R>> for StreamIntoIter<R> {
+    fn from(value: TryStreamIter<R>) -> Self {
+        Self { inner: value }
+    }
+}
+
+impl<R: Read> Iterator for StreamIntoIter<R> {
+    type Item = Stream;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()?.ok()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<R: Read> ExactSizeIterator for StreamIntoIter<R> {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+impl<R: Read> FusedIterator for StreamIntoIter<R> {}
+
+/// A fallible iterator over sound bank streams.
+///
+/// This type is returned from [`Bank::try_streams`].
+/// When iterating, `Some(Ok(Stream))` is returned if a stream was successfully read from the sound bank,
+/// and `Some(Err(StreamReadError))` if reading failed (e.g. because the file was truncated).
+/// After an error is returned, the iterator is exhausted and only returns `None`.
+///
+/// [`Bank::try_streams`]: crate::Bank::try_streams
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TryStreamIter<R: Read> {
     index: u32,
+    failed: bool,
     format: AudioFormat,
-    flags: u32,
+    flags: BankFlags,
     info: Box<[StreamInfo]>,
     reader: Reader<R>,
+    max_allocation: u64,
 }
 
-impl<R: Read> StreamIntoIter<R> {
+impl<R: Read> TryStreamIter<R> {
     pub(crate) fn new(
         format: AudioFormat,
-        flags: u32,
+        flags: BankFlags,
         info: Box<[StreamInfo]>,
         reader: Reader<R>,
+        max_allocation: u64,
     ) -> Self {
         Self {
             index: 0,
+            failed: false,
             format,
             flags,
             info,
             reader,
+            max_allocation,
         }
     }
-}
 
-impl<R: Read> Iterator for StreamIntoIter<R> {
-    type Item = Stream;
+    fn read_stream(&mut self, info: StreamInfo) -> Result<Stream, ReadError> {
+        let size = info.size.get();
+        let start_pos = self.reader.position();
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let stream = self.info.get(self.index as usize).cloned().and_then(|info| {
-            let size = info.size.get() as usize;
-            let start_pos = self.reader.position();
+        let data = self.reader.take_within(size as usize, self.max_allocation)?;
+        self.reader.advance_to(start_pos + u64::from(size))?;
 
-            let stream =
-                self.reader.take(size).ok().map(|data| {
-                    Stream::new(self.format, self.flags, info, data.into_boxed_slice())
-                });
+        Ok(Stream::new(self.format, self.flags, info, data.into_boxed_slice()))
+    }
+}
 
-            self.reader.advance_to(start_pos + size).ok()?;
+impl<R: Read> Iterator for TryStreamIter<R> {
+    type Item = Result<Stream, StreamReadError>;
 
-            stream
-        });
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
 
+        let index = self.index;
+        let info = self.info.get(index as usize).cloned()?;
         self.index += 1;
 
-        stream
+        let stream = self.read_stream(info).map_err(StreamReadError::factory(index));
+
+        // a failed read leaves the reader at an unknown position, so the following streams can't be read
+        self.failed = stream.is_err();
+
+        Some(stream)
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let len = self.info.len();
+        let len = self.len();
         (len, Some(len))
     }
 }
 
-impl<R: Read> ExactSizeIterator for StreamIntoIter<R> {
+impl<R: Read> ExactSizeIterator for TryStreamIter<R> {
     fn len(&self) -> usize {
-        self.info.len()
+        if self.failed {
+            0
+        } else {
+            self.info.len() - self.index as usize
+        }
     }
 }
+
+impl<R: Read> FusedIterator for TryStreamIter<R> {}
@@ -0,0 +1,228 @@
+use crate::encode::VORBIS_LOOKUP;
+use crate::header::{AudioFormat, Header, Loop};
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    num::NonZeroU32,
+};
+
+/// The result of checking a sound bank for inconsistencies, returned from [`Bank::validate`].
+///
+/// [`Bank::validate`]: crate::Bank::validate
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationReport {
+    issues: Box<[ValidationIssue]>,
+}
+
+impl ValidationReport {
+    /// Returns `true` if no issues were found.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Returns the issues that were found, in the order they were checked.
+    #[must_use]
+    pub fn issues(&self) -> &[ValidationIssue] {
+        &self.issues
+    }
+}
+
+impl Display for ValidationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        if self.is_valid() {
+            return f.write_str("no issues found");
+        }
+
+        for (index, issue) in self.issues.iter().enumerate() {
+            if index > 0 {
+                f.write_str("\n")?;
+            }
+            issue.fmt(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Represents an inconsistency found when validating a sound bank.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ValidationIssue {
+    /// The sizes of all streams did not add up to the total size of stream data given by the file header.
+    /// This happens when the data of the first stream does not start at offset 0,
+    /// since sound banks with stream data offsets that are not increasing fail to parse, even in non-strict mode.
+    StreamSizeMismatch {
+        /// The total size of stream data given by the file header, in bytes.
+        expected: u32,
+        /// The sum of the sizes of all streams, in bytes.
+        actual: u64,
+    },
+    /// The number of samples in a stream did not match the size of its data.
+    ///
+    /// This is only checked for formats where samples have a fixed size, such as PCM and ADPCM.
+    ImplausibleSampleCount {
+        /// The index of the stream.
+        index: u32,
+        /// The number of samples in the stream.
+        num_samples: NonZeroU32,
+        /// The size of the stream data, in bytes.
+        size: NonZeroU32,
+    },
+    /// A loop extended past the end of its stream.
+    LoopOutOfRange {
+        /// The index of the stream.
+        index: u32,
+        /// The loop of the stream.
+        stream_loop: Loop,
+        /// The number of samples in the stream.
+        num_samples: NonZeroU32,
+    },
+    /// A Vorbis stream did not contain the CRC32 of its setup header.
+    MissingVorbisCrc {
+        /// The index of the stream.
+        index: u32,
+    },
+    /// The CRC32 of a Vorbis stream's setup header did not match any known setup header,
+    /// so the stream can't be encoded.
+    UnknownVorbisCrc {
+        /// The index of the stream.
+        index: u32,
+        /// The CRC32 found in the stream header.
+        crc32: u32,
+    },
+    /// The stream names did not take up exactly the size of the name table given by the file header.
+    NameTableNotConsumed {
+        /// The size of the name table given by the file header, in bytes.
+        expected: u32,
+        /// The number of bytes read from the name table.
+        actual: u64,
+    },
+}
+
+impl Display for ValidationIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::StreamSizeMismatch { expected, actual } => f.write_fmt(format_args!(
+                "sizes of streams added up to {actual} bytes instead of the total size of stream data ({expected} bytes)"
+            )),
+            Self::ImplausibleSampleCount {
+                index,
+                num_samples,
+                size,
+            } => f.write_fmt(format_args!(
+                "number of samples ({num_samples}) did not match size of stream data ({size} bytes) - stream at index {index}"
+            )),
+            Self::LoopOutOfRange {
+                index,
+                stream_loop,
+                num_samples,
+            } => f.write_fmt(format_args!(
+                "loop (start {}, end {}) extended past the end of the stream ({num_samples} samples) - stream at index {index}",
+                stream_loop.start(),
+                stream_loop.end(),
+            )),
+            Self::MissingVorbisCrc { index } => f.write_fmt(format_args!(
+                "CRC32 of Vorbis setup header was missing - stream at index {index}"
+            )),
+            Self::UnknownVorbisCrc { index, crc32 } => f.write_fmt(format_args!(
+                "CRC32 of Vorbis setup header was not recognized (0x{crc32:08x}) - stream at index {index}"
+            )),
+            Self::NameTableNotConsumed { expected, actual } => f.write_fmt(format_args!(
+                "{actual} bytes were read from name table instead of its size ({expected} bytes)"
+            )),
+        }
+    }
+}
+
+// Stream data is padded to a multiple of this many bytes.
+const DATA_ALIGNMENT: u64 = 32;
+
+pub(crate) fn validate(header: &Header) -> ValidationReport {
+    let base = &header.base;
+    let mut issues = Vec::new();
+
+    let total_size: u64 = header.stream_info.iter().map(|info| u64::from(info.size.get())).sum();
+
+    if total_size != u64::from(base.total_stream_size.get()) {
+        issues.push(ValidationIssue::StreamSizeMismatch {
+            expected: base.total_stream_size.get(),
+            actual: total_size,
+        });
+    }
+
+    for (info, index) in header.stream_info.iter().zip(0..) {
+        if !is_plausible_sample_count(
+            header.format,
+            info.num_samples,
+            info.channels.get(),
+            info.size,
+        ) {
+            issues.push(ValidationIssue::ImplausibleSampleCount {
+                index,
+                num_samples: info.num_samples,
+                size: info.size,
+            });
+        }
+
+        if let Some(stream_loop) = info.stream_loop {
+            if !stream_loop.is_within(info.num_samples) {
+                issues.push(ValidationIssue::LoopOutOfRange {
+                    index,
+                    stream_loop,
+                    num_samples: info.num_samples,
+                });
+            }
+        }
+
+        if header.format == AudioFormat::Vorbis {
+            match info.vorbis_crc32 {
+                None => issues.push(ValidationIssue::MissingVorbisCrc { index }),
+                Some(crc32) if !VORBIS_LOOKUP.contains_key(&crc32) => {
+                    issues.push(ValidationIssue::UnknownVorbisCrc { index, crc32 });
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    if header.name_table_read != u64::from(base.name_table_size) {
+        issues.push(ValidationIssue::NameTableNotConsumed {
+            expected: base.name_table_size,
+            actual: header.name_table_read,
+        });
+    }
+
+    ValidationReport {
+        issues: issues.into_boxed_slice(),
+    }
+}
+
+// For formats where samples have a fixed size, the number of samples determines the size of the stream data.
+// PCM data is only padded to the next 32-byte boundary, while ADPCM data can also contain padding between frames.
+fn is_plausible_sample_count(
+    format: AudioFormat,
+    num_samples: NonZeroU32,
+    channels: u8,
+    size: NonZeroU32,
+) -> bool {
+    let num_samples = u64::from(num_samples.get());
+    let channels = u64::from(channels);
+    let size = u64::from(size.get());
+
+    // (samples per frame, bytes per frame) for each channel
+    let frame = match format {
+        AudioFormat::GcAdpcm => (14, 8),
+        AudioFormat::Vag | AudioFormat::HeVag => (28, 16),
+        format => {
+            return match format.pcm_byte_depth() {
+                Some(depth) => {
+                    let expected = num_samples * channels * u64::from(depth.get());
+                    expected <= size && size - expected < DATA_ALIGNMENT
+                }
+                None => true,
+            };
+        }
+    };
+
+    num_samples.div_ceil(frame.0) * frame.1 * channels <= size
+}